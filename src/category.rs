@@ -0,0 +1,182 @@
+use colored::{Color, ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum CategoryClass {
+    Productive,
+    Chore,
+    Entertainment,
+    Custom,
+}
+
+impl CategoryClass {
+    pub const ALL: [CategoryClass; 4] = [
+        CategoryClass::Productive,
+        CategoryClass::Chore,
+        CategoryClass::Entertainment,
+        CategoryClass::Custom,
+    ];
+
+    pub fn parse(input: &str) -> Option<CategoryClass> {
+        match input.to_lowercase().as_str() {
+            "productive" | "p" => Some(CategoryClass::Productive),
+            "chore" | "chores" | "ch" => Some(CategoryClass::Chore),
+            "entertainment" | "e" => Some(CategoryClass::Entertainment),
+            "custom" | "other" => Some(CategoryClass::Custom),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CategoryClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CategoryClass::Productive => "productive",
+            CategoryClass::Chore => "chore",
+            CategoryClass::Entertainment => "entertainment",
+            CategoryClass::Custom => "custom",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Category {
+    pub code: String,
+    pub name: String,
+    pub class: CategoryClass,
+    pub color: String,
+    #[serde(default)]
+    pub retired: bool,
+}
+
+/// The task codes known to the program. Retired categories stay in the
+/// registry so that history recorded under them keeps its class and color.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct Categories(Vec<Category>);
+
+impl Default for Categories {
+    fn default() -> Self {
+        let category = |code: &str, name: &str, class, color: &str| Category {
+            code: code.to_string(),
+            name: name.to_string(),
+            class,
+            color: color.to_string(),
+            retired: false,
+        };
+        Categories(vec![
+            category("C", "Coding", CategoryClass::Productive, "blue"),
+            category("R", "Reading", CategoryClass::Productive, "cyan"),
+            category("A", "Action", CategoryClass::Productive, "magenta"),
+            category("W", "Writing", CategoryClass::Productive, "green"),
+            category("L", "Learning", CategoryClass::Productive, "bright blue"),
+            category("Ch", "Chores", CategoryClass::Chore, "yellow"),
+            category("E", "Entertainment", CategoryClass::Entertainment, "red"),
+        ])
+    }
+}
+
+impl Categories {
+    pub fn all(&self) -> &[Category] {
+        &self.0
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Category> {
+        self.0.iter().filter(|category| !category.retired)
+    }
+
+    pub fn by_code(&self, code: &str) -> Option<&Category> {
+        self.active()
+            .find(|category| category.code.eq_ignore_ascii_case(code))
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&Category> {
+        self.0
+            .iter()
+            .find(|category| category.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Looks up an active category by code first, then by name.
+    pub fn find(&self, code_or_name: &str) -> Option<&Category> {
+        self.by_code(code_or_name).or_else(|| {
+            self.active()
+                .find(|category| category.name.eq_ignore_ascii_case(code_or_name))
+        })
+    }
+
+    /// Class of a category by name. Names that are no longer registered are
+    /// treated as productive, which is how they were recorded originally.
+    pub fn class_of(&self, name: &str) -> CategoryClass {
+        self.by_name(name)
+            .map(|category| category.class)
+            .unwrap_or(CategoryClass::Productive)
+    }
+
+    pub fn paint(&self, name: &str) -> ColoredString {
+        match self
+            .by_name(name)
+            .and_then(|category| category.color.parse::<Color>().ok())
+        {
+            Some(color) => name.color(color),
+            None => name.normal(),
+        }
+    }
+
+    pub fn prompt_list(&self) -> String {
+        self.active()
+            .map(|category| format!("{} for {}", category.code, category.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn add(&mut self, category: Category) -> Result<(), String> {
+        if category.code.eq_ignore_ascii_case("x") {
+            return Err("X is reserved for exiting the prompt.".to_string());
+        }
+        if self.by_code(&category.code).is_some() {
+            return Err(format!("The code {} is already in use.", category.code));
+        }
+        if let Some(existing) = self.by_name(&category.name) {
+            return Err(format!(
+                "A category named {} already exists (code {}).",
+                existing.name, existing.code
+            ));
+        }
+        if category.color.parse::<Color>().is_err() {
+            return Err(format!("Unknown color '{}'.", category.color));
+        }
+        self.0.push(category);
+        Ok(())
+    }
+
+    /// Renames the active category with the given code and returns its old name.
+    pub fn rename(&mut self, code: &str, new_name: &str) -> Result<String, String> {
+        if let Some(existing) = self.by_name(new_name) {
+            if !existing.code.eq_ignore_ascii_case(code) {
+                return Err(format!(
+                    "A category named {} already exists.",
+                    existing.name
+                ));
+            }
+        }
+        let category = self
+            .0
+            .iter_mut()
+            .find(|category| !category.retired && category.code.eq_ignore_ascii_case(code))
+            .ok_or_else(|| format!("No active category with code {}.", code))?;
+        Ok(std::mem::replace(&mut category.name, new_name.to_string()))
+    }
+
+    pub fn retire(&mut self, code: &str) -> Result<String, String> {
+        let category = self
+            .0
+            .iter_mut()
+            .find(|category| !category.retired && category.code.eq_ignore_ascii_case(code))
+            .ok_or_else(|| format!("No active category with code {}.", code))?;
+        category.retired = true;
+        Ok(category.name.clone())
+    }
+}
@@ -0,0 +1,98 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use terminal_size::{terminal_size, Width};
+
+/// Width used when neither the terminal nor `COLUMNS` says how wide it is.
+const DEFAULT_WIDTH: usize = 80;
+const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const PLAIN_SPARKS: [char; 8] = ['_', '.', ':', '-', '=', '+', '*', '#'];
+
+/// Where charts are drawn. On a terminal they use block characters and fit
+/// its width; otherwise, such as when piped to a file, they are plain ASCII
+/// at a fixed width.
+pub struct Canvas {
+    pub width: usize,
+    pub fancy: bool,
+}
+
+impl Canvas {
+    pub fn detect() -> Self {
+        let fancy = io::stdout().is_terminal();
+        let width = if fancy {
+            terminal_size()
+                .map(|(Width(columns), _)| columns as usize)
+                .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+                .filter(|columns| *columns > 0)
+                .unwrap_or(DEFAULT_WIDTH)
+        } else {
+            DEFAULT_WIDTH
+        };
+        Canvas { width, fancy }
+    }
+
+    /// A horizontal bar `length` characters long at most, in proportion to
+    /// `value` out of `max`.
+    pub fn bar(&self, value: u64, max: u64, length: usize) -> String {
+        let eighths = (value * length as u64 * 8 / max.max(1)) as usize;
+        if !self.fancy {
+            return "#".repeat(eighths / 8);
+        }
+        let mut bar = "█".repeat(eighths / 8);
+        let partial = eighths % 8;
+        if partial > 0 {
+            bar.push(EIGHTHS[partial]);
+        }
+        bar
+    }
+
+    /// One character per value, from lowest to highest. Only the latest
+    /// values are kept when there are more than the canvas is wide.
+    pub fn sparkline(&self, values: &[u64]) -> String {
+        let values = &values[values.len().saturating_sub(self.width)..];
+        let max = values.iter().copied().max().unwrap_or(0).max(1);
+        let sparks = if self.fancy { SPARKS } else { PLAIN_SPARKS };
+        values
+            .iter()
+            .map(|value| sparks[(value * 7 / max) as usize])
+            .collect()
+    }
+
+    /// A column chart, `height` rows tall, with a two-character label under
+    /// each column. Returns the lines to print, top row first. Only the
+    /// latest columns are kept when they do not all fit.
+    pub fn columns(&self, columns: &[(String, u64)], height: usize) -> Vec<String> {
+        let fitting = (self.width / 3).max(1);
+        let columns = &columns[columns.len().saturating_sub(fitting)..];
+        let max = columns.iter().map(|(_, value)| *value).max().unwrap_or(0);
+        let full = if self.fancy { "██ " } else { "## " };
+        let half = if self.fancy { "▄▄ " } else { ".. " };
+
+        let mut lines = Vec::new();
+        for row in (0..height).rev() {
+            let line: String = columns
+                .iter()
+                .map(|(_, value)| {
+                    let halves = (value * height as u64 * 2 / max.max(1)) as usize;
+                    if halves >= row * 2 + 2 {
+                        full
+                    } else if halves == row * 2 + 1 {
+                        half
+                    } else {
+                        "   "
+                    }
+                })
+                .collect();
+            lines.push(line.trim_end().to_string());
+        }
+        lines.push(
+            columns
+                .iter()
+                .map(|(label, _)| format!("{:<3}", label))
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        lines
+    }
+}
@@ -0,0 +1,259 @@
+use clap::{Parser, Subcommand};
+
+/// Track where your day goes, one task at a time.
+///
+/// Run without a command to log tasks interactively.
+#[derive(Parser, Debug)]
+#[command(name = "crawl-todo", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Log tasks one after another by answering prompts
+    Interactive,
+    /// Show today's progress and any running timer
+    Status,
+    /// Start a timer for a category and exit; stop it later with `stop`
+    Start {
+        /// Category code or name
+        category: String,
+        /// Optional note for the session
+        note: Vec<String>,
+    },
+    /// Stop the running timer and record the session
+    Stop {
+        /// Optional note, replacing the one given to `start`
+        note: Vec<String>,
+    },
+    /// Record a finished session in one go, e.g. `log coding 09:15-11:40` or `log reading 45m`
+    Log {
+        /// Category code or name
+        category: String,
+        /// A time range such as 09:15-11:40, or a length such as 45m or 1h30 ending now
+        when: String,
+        /// Optional note for the session
+        note: Vec<String>,
+        /// Day to log for: YYYY-MM-DD, yesterday, -3d, ...
+        #[arg(long, allow_hyphen_values = true)]
+        date: Option<String>,
+    },
+    /// List, edit or delete logged sessions
+    #[command(subcommand)]
+    Entry(EntryCommand),
+    /// Revert the last change to logged sessions
+    Undo,
+    /// Mark days as days off, holidays or untracked
+    #[command(subcommand)]
+    Day(DayCommand),
+    /// Show time spent over a period
+    Report {
+        /// daily, weekly, monthly, yearly, all, last-week, last-month, ytd or last-30d (any number of days)
+        #[arg(default_value = "weekly")]
+        period: String,
+        /// First day of a custom range: YYYY-MM-DD, yesterday, -30d, ...
+        #[arg(long, allow_hyphen_values = true, conflicts_with = "period")]
+        from: Option<String>,
+        /// Last day of a custom range (default: today)
+        #[arg(long, allow_hyphen_values = true, requires = "from")]
+        to: Option<String>,
+        /// Order of the category table: time, name or class
+        #[arg(long, default_value = "time", value_parser = ["time", "name", "class"])]
+        sort: String,
+        /// Add bar charts and a sparkline of productive time per day
+        #[arg(long)]
+        chart: bool,
+        /// Days covered by the sparkline, up to a year
+        #[arg(
+            long,
+            default_value_t = 30,
+            requires = "chart",
+            value_parser = clap::value_parser!(u16).range(1..=366)
+        )]
+        sparkline_days: u16,
+    },
+    /// Manage to-do items
+    #[command(subcommand)]
+    Todo(TodoCommand),
+    /// Manage task categories and their codes
+    #[command(subcommand)]
+    Category(CategoryCommand),
+    /// View or change settings
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Set daily or weekly time goals for categories
+    #[command(subcommand)]
+    Goal(GoalCommand),
+    /// View or change working hours for each weekday
+    #[command(subcommand)]
+    Schedule(ScheduleCommand),
+    /// Upgrade the settings file to the current schema
+    Migrate {
+        /// Show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List backups of the settings file, or restore one
+    Restore {
+        /// Number of the backup to restore, as shown in the list
+        number: Option<usize>,
+    },
+    #[command(hide = true)]
+    Daily,
+    #[command(hide = true)]
+    Weekly,
+    #[command(hide = true)]
+    Monthly,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TodoCommand {
+    /// Add a new item
+    Add {
+        #[arg(required = true, num_args = 1..)]
+        title: Vec<String>,
+        /// Category name or code the item belongs to
+        #[arg(long, short)]
+        category: Option<String>,
+    },
+    /// List open items
+    List {
+        /// Include done and cancelled items
+        #[arg(long)]
+        all: bool,
+    },
+    /// Mark an item as in progress
+    Start { id: u32 },
+    /// Mark an item as done
+    Done { id: u32 },
+    /// Mark an item as cancelled
+    Cancel { id: u32 },
+    /// Mark a done or cancelled item as open again
+    Reopen { id: u32 },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum EntryCommand {
+    /// List a day's sessions with their ids
+    List {
+        /// Day to list instead of today: YYYY-MM-DD, yesterday, -3d, ...
+        #[arg(long, allow_hyphen_values = true)]
+        date: Option<String>,
+    },
+    /// Change the times, category or note of a session
+    Edit {
+        id: u32,
+        /// New start time (HH:MM)
+        #[arg(long)]
+        start: Option<String>,
+        /// New end time (HH:MM)
+        #[arg(long)]
+        end: Option<String>,
+        /// New category code or name
+        #[arg(long, short)]
+        category: Option<String>,
+        /// New note; an empty string removes it
+        #[arg(long)]
+        note: Option<String>,
+        /// Move the session to another day, keeping its times
+        #[arg(long, allow_hyphen_values = true)]
+        date: Option<String>,
+    },
+    /// Delete a session and take its time off the totals
+    Delete { id: u32 },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DayCommand {
+    /// Say what kind of day one or more days were
+    Mark {
+        /// tracked, untracked, day-off or holiday
+        status: String,
+        /// Days to mark: YYYY-MM-DD, yesterday, -3d, ... (default: today)
+        #[arg(allow_hyphen_values = true)]
+        dates: Vec<String>,
+    },
+    /// List recent days and how they are marked
+    List {
+        /// How many days to show
+        #[arg(long, default_value_t = 14)]
+        days: usize,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CategoryCommand {
+    /// List all categories, including retired ones
+    List,
+    /// Register a new category
+    Add {
+        code: String,
+        name: String,
+        /// productive, chore, entertainment or custom
+        class: String,
+        #[arg(default_value = "white")]
+        color: String,
+    },
+    /// Give a category a new display name, including in past days
+    Rename { code: String, new_name: String },
+    /// Stop offering a category; its history is kept
+    Retire { code: String },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Print every setting
+    Show,
+    /// Change a setting
+    Set {
+        /// end-time, start-time, timezone, day-start, midnight, gaps or backups
+        key: String,
+        value: String,
+    },
+    /// Clear the end time or default start time
+    Unset {
+        /// end-time or start-time
+        key: String,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ScheduleCommand {
+    /// Print the working hours for every weekday
+    Show,
+    /// Set working hours for one or more weekdays
+    Set {
+        /// fri, mon-thu, sat,sun, weekdays, weekend or all
+        days: String,
+        /// Hours such as 09:00-17:00, or off
+        hours: String,
+    },
+    /// Go back to the end-time and start-time settings for these weekdays
+    Clear {
+        /// fri, mon-thu, sat,sun, weekdays, weekend or all
+        days: String,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum GoalCommand {
+    /// List goals with their numbers
+    List,
+    /// Add a goal, e.g. `goal add coding 4h` or `goal add entertainment 2h --at-most`
+    Add {
+        /// Category code or name
+        category: String,
+        /// Amount of time such as 4h, 90m or 1h30
+        length: String,
+        /// day or week
+        #[arg(long, default_value = "day")]
+        per: String,
+        /// Treat the amount as a limit rather than a target
+        #[arg(long)]
+        at_most: bool,
+    },
+    /// Remove a goal by its number in `goal list`
+    Remove { number: usize },
+}
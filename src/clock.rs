@@ -0,0 +1,226 @@
+use chrono::{Days, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, SubsecRound, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// The zone used to turn the system clock into the wall-clock time that days
+/// and sessions are recorded in. Recorded times are kept as wall-clock values,
+/// so changing the zone only affects entries made afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Timezone {
+    #[default]
+    Local,
+    Named(Tz),
+}
+
+impl Timezone {
+    pub fn now(self) -> NaiveDateTime {
+        let now = match self {
+            Timezone::Local => Local::now().naive_local(),
+            Timezone::Named(tz) => Utc::now().with_timezone(&tz).naive_local(),
+        };
+        now.trunc_subsecs(0)
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if input.eq_ignore_ascii_case("local") {
+            return Ok(Timezone::Local);
+        }
+        input.parse::<Tz>().map(Timezone::Named).map_err(|_| {
+            format!(
+                "Unknown timezone '{}'. Use an IANA name such as Europe/Berlin, or 'local'.",
+                input
+            )
+        })
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timezone::Local => write!(f, "local"),
+            Timezone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+impl Serialize for Timezone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parses a calendar date in ISO-8601 or the `%d/%m/%Y` form older files used.
+pub fn parse_date(input: &str) -> Option<NaiveDate> {
+    let input = input.trim();
+    ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%Y/%m/%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(input, format).ok())
+}
+
+/// Parses a day as typed on the command line: an absolute date, `today`,
+/// `yesterday`, or a number of days back such as `-3d`.
+pub fn parse_day(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        _ => match input.strip_prefix('-') {
+            Some(days) => {
+                let days: u64 = days.strip_suffix('d').unwrap_or(days).parse().ok()?;
+                today.checked_sub_days(Days::new(days))
+            }
+            None => parse_date(&input),
+        },
+    }
+}
+
+/// Parses a time of day with or without seconds.
+pub fn parse_time(input: &str) -> Option<NaiveTime> {
+    let input = input.trim();
+    ["%H:%M:%S", "%H:%M"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(input, format).ok())
+}
+
+/// Parses a time typed by the user: `HH:MM`, `HHMM`, or `now` (also the empty
+/// string) for the current time.
+pub fn parse_time_input(input: &str, now: NaiveTime) -> Option<NaiveTime> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("now") {
+        Some(now)
+    } else if input.len() == 4 && input.chars().all(|c| c.is_ascii_digit()) {
+        NaiveTime::parse_from_str(&format!("{}:{}", &input[0..2], &input[2..4]), "%H:%M").ok()
+    } else {
+        NaiveTime::parse_from_str(input, "%H:%M").ok()
+    }
+}
+
+/// The day `at` belongs to when days start at `day_start` rather than at
+/// midnight.
+pub fn logical_date(at: NaiveDateTime, day_start: NaiveTime) -> NaiveDate {
+    (at - day_start.signed_duration_since(NaiveTime::MIN)).date()
+}
+
+/// Where `time` falls on the calendar during the day `date`. Times before
+/// `day_start` belong to the small hours of the next calendar date.
+pub fn on_day(date: NaiveDate, time: NaiveTime, day_start: NaiveTime) -> NaiveDateTime {
+    if time < day_start {
+        date.succ_opt().unwrap_or(date).and_time(time)
+    } else {
+        date.and_time(time)
+    }
+}
+
+/// Puts a start and end time on the calendar during the day `date`. An end
+/// time before the start time is taken to be in the following day.
+pub fn time_span(
+    date: NaiveDate,
+    start: NaiveTime,
+    end: NaiveTime,
+    day_start: NaiveTime,
+) -> (NaiveDateTime, NaiveDateTime) {
+    let start = on_day(date, start, day_start);
+    let mut end = on_day(date, end, day_start);
+    if end < start {
+        end += Duration::days(1);
+    }
+    (start, end)
+}
+
+/// The longest length of time `parse_duration` accepts: a week, enough for
+/// weekly goals.
+pub const MAX_DURATION_MINUTES: i64 = 7 * 24 * 60;
+
+/// Parses a length of time such as `45m`, `2h`, `1h30m` or `1h30`. Minutes
+/// after hours must be below 60.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    let (hours, minutes) = match input.split_once('h') {
+        Some((hours, minutes)) => (hours, minutes.strip_suffix('m').unwrap_or(minutes)),
+        None => ("0", input.strip_suffix('m')?),
+    };
+    let number = |text: &str| -> Option<i64> {
+        if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        text.parse().ok()
+    };
+    let hours = number(hours)?;
+    let minutes = if minutes.is_empty() {
+        0
+    } else {
+        number(minutes)?
+    };
+    if input.contains('h') && minutes >= 60 {
+        return None;
+    }
+    let total = hours.checked_mul(60)?.checked_add(minutes)?;
+    if total <= 0 || total > MAX_DURATION_MINUTES {
+        return None;
+    }
+    Duration::try_minutes(total)
+}
+
+/// Writes a number of minutes the way `parse_duration` reads it, e.g. `1h30m`.
+pub fn format_duration(minutes: u64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h{}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("45m"), Some(Duration::minutes(45)));
+        assert_eq!(parse_duration("2h"), Some(Duration::minutes(120)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1H30"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+    }
+
+    #[test]
+    fn rejects_bad_durations() {
+        for input in [
+            "", "0m", "0h", "h30", "1h-5", "1h75", "+5m", "-5m", "5", "1h30x", "168h1m",
+        ] {
+            assert_eq!(parse_duration(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn huge_durations_do_not_panic() {
+        assert_eq!(parse_duration("999999999999999999h"), None);
+        assert_eq!(parse_duration("9999999999999999h"), None);
+        assert_eq!(parse_duration("99999999999999999999999m"), None);
+    }
+
+    #[test]
+    fn formats_durations_the_way_they_are_parsed() {
+        for minutes in [5, 60, 90, 600] {
+            let text = format_duration(minutes);
+            assert_eq!(
+                parse_duration(&text),
+                Some(Duration::minutes(minutes as i64))
+            );
+        }
+    }
+}
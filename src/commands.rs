@@ -0,0 +1,922 @@
+use crate::category::{Category, CategoryClass};
+use crate::cli::{
+    CategoryCommand, ConfigCommand, DayCommand, EntryCommand, GoalCommand, ScheduleCommand,
+    TodoCommand,
+};
+use crate::clock::{parse_day, parse_duration, parse_time, parse_time_input, time_span, Timezone};
+use crate::goal::{Goal, GoalKind, GoalPeriod};
+use crate::migration::CURRENT_VERSION;
+use crate::schedule::{parse_weekdays, ScheduledDay, WEEK};
+use crate::serialization::{
+    list_backups, load_user_settings, preview_migration, restore_backup, update_user_settings,
+    StorageError,
+};
+use crate::stats::{display_date_range_stats, display_stats, ReportOptions, TaskSummary};
+use crate::task::{ActiveSession, DayStatus, MidnightRule, Session, UserSettings};
+use crate::todo::TodoStatus;
+use crate::user_interaction::{display_summary, display_todo, prompt_task, warn_if_overlapping};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use colored::*;
+use std::fmt;
+
+/// Why a command failed. Either way the process exits with a non-zero code.
+#[derive(Debug)]
+pub enum CommandError {
+    Storage(StorageError),
+    Invalid(String),
+}
+
+impl CommandError {
+    pub fn is_corrupt_file(&self) -> bool {
+        matches!(self, CommandError::Storage(error) if error.is_corrupt_file())
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Storage(error) => write!(f, "{}", error),
+            CommandError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<StorageError> for CommandError {
+    fn from(error: StorageError) -> Self {
+        CommandError::Storage(error)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Invalid(message)
+    }
+}
+
+pub fn run_interactive(user_settings: &mut UserSettings) -> Result<(), CommandError> {
+    let current_time = user_settings.now();
+    let start_time = user_settings.get_start_time()?;
+    let end_of_day = user_settings.get_end_time()?;
+    if let Some(end_of_day) = end_of_day {
+        let day_duration =
+            end_of_day - user_settings.on_day(user_settings.current_date(), start_time);
+        let total_hours = day_duration.num_hours();
+        let total_minutes = day_duration.num_minutes() % 60;
+
+        println!(
+            "{} hours and {} minutes to seize the day",
+            total_hours, total_minutes
+        );
+    }
+
+    display_summary(user_settings, &current_time, Some(start_time), end_of_day);
+
+    while prompt_task(user_settings)? {}
+
+    display_stats(user_settings, "daily", &ReportOptions::default())?;
+    Ok(())
+}
+
+pub fn run_status(user_settings: &UserSettings) {
+    let current_time = user_settings.now();
+    let date = user_settings.current_date();
+    let end_of_day = user_settings
+        .scheduled_end(date)
+        .map(|end_time| user_settings.on_day(date, end_time));
+    display_summary(
+        user_settings,
+        &current_time,
+        user_settings.today.start_time,
+        end_of_day,
+    );
+}
+
+fn note_from_words(words: &[String]) -> Option<String> {
+    let note = words.join(" ");
+    if note.trim().is_empty() {
+        None
+    } else {
+        Some(note)
+    }
+}
+
+pub fn run_start(
+    user_settings: &mut UserSettings,
+    category: &str,
+    note: &[String],
+) -> Result<(), CommandError> {
+    let category = user_settings
+        .categories
+        .find(category)
+        .map(|found| found.name.clone())
+        .ok_or_else(|| format!("Unknown category '{}'.", category))?;
+    let now = user_settings.now();
+    let note = note_from_words(note);
+
+    update_user_settings(user_settings, |settings| match &settings.active_session {
+        Some(active) => Err(format!(
+            "A timer for {} has been running since {}. Stop it first.",
+            active.category,
+            active.start.format("%H:%M")
+        )),
+        None => {
+            settings.active_session = Some(ActiveSession {
+                category: category.clone(),
+                start: now,
+                note,
+            });
+            Ok(())
+        }
+    })??;
+    println!("Started {} at {}.", category, now.format("%H:%M"));
+    Ok(())
+}
+
+pub fn run_stop(user_settings: &mut UserSettings, note: &[String]) -> Result<(), CommandError> {
+    let now = user_settings.now();
+    let note = note_from_words(note);
+    let (session, recorded) =
+        update_user_settings(user_settings, |settings| settings.stop_timer(now, note))??;
+    let minutes = session.minutes();
+    println!(
+        "Stopped {} {} after {} hours and {} minutes ({} - {}).",
+        entry_ids(&recorded),
+        session.category,
+        minutes / 60,
+        minutes % 60,
+        session.start.format("%H:%M"),
+        session.end.format("%H:%M")
+    );
+    Ok(())
+}
+
+/// Lists the ids a session was stored under, e.g. `#4` or `#4 and #5` when it
+/// was split at midnight.
+fn entry_ids(sessions: &[Session]) -> String {
+    sessions
+        .iter()
+        .map(|session| format!("#{}", session.id))
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// The days sessions were filed under, e.g. `16/10/2026 and 17/10/2026` for
+/// a session split at midnight.
+fn filed_dates(user_settings: &UserSettings, sessions: &[Session]) -> String {
+    let mut dates: Vec<NaiveDate> = sessions
+        .iter()
+        .map(|session| user_settings.filing_date(session))
+        .collect();
+    dates.dedup();
+    dates
+        .iter()
+        .map(|date| date.format("%d/%m/%Y").to_string())
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Reads a `--date` value, defaulting to today. Time cannot be logged for days
+/// that have not started yet.
+fn resolve_date(user_settings: &UserSettings, date: Option<&str>) -> Result<NaiveDate, String> {
+    let today = user_settings.current_date();
+    let Some(text) = date else {
+        return Ok(today);
+    };
+    let date = parse_day(text, today).ok_or_else(|| {
+        format!(
+            "'{}' is not a date. Use YYYY-MM-DD, today, yesterday or -3d.",
+            text
+        )
+    })?;
+    if date > today {
+        return Err(format!("{} is in the future.", date.format("%d/%m/%Y")));
+    }
+    Ok(date)
+}
+
+/// Prints a report for a named period, or for the days from `from` to `to`
+/// when a range is given.
+pub fn run_report(
+    user_settings: &UserSettings,
+    period: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    options: ReportOptions,
+) -> Result<(), CommandError> {
+    if from.is_none() {
+        return Ok(display_stats(user_settings, period, &options)?);
+    }
+    let from_date = resolve_date(user_settings, from)?;
+    let to_date = resolve_date(user_settings, to)?;
+    if from_date > to_date {
+        return Err(CommandError::Invalid(format!(
+            "The range starts on {} but ends earlier, on {}.",
+            from_date.format("%d/%m/%Y"),
+            to_date.format("%d/%m/%Y")
+        )));
+    }
+    display_date_range_stats(user_settings, from_date, to_date, &options);
+    Ok(())
+}
+
+/// Records a session after the fact. `when` is either a `start-end` range,
+/// each side read like an answer to the interactive prompts, or a length of
+/// time that ends now. Without `date`, a range goes on the most recent day on
+/// which it has already started.
+pub fn run_log(
+    user_settings: &mut UserSettings,
+    category: &str,
+    when: &str,
+    note: &[String],
+    date: Option<&str>,
+) -> Result<(), CommandError> {
+    let date_given = date.is_some();
+    let date = resolve_date(user_settings, date)?;
+    let category = user_settings
+        .categories
+        .find(category)
+        .map(|found| found.name.clone())
+        .ok_or_else(|| format!("Unknown category '{}'.", category))?;
+    let now = user_settings.now();
+    let invalid = || {
+        format!(
+            "Could not read '{}'. Use a range like 09:15-11:40 or a length like 45m or 1h30.",
+            when
+        )
+    };
+
+    let (start, end) = match when.split_once('-') {
+        Some((start, end)) => {
+            let start = parse_time_input(start, now.time()).ok_or_else(invalid)?;
+            let end = parse_time_input(end, now.time()).ok_or_else(invalid)?;
+            if date_given {
+                time_span(date, start, end, user_settings.day_starts_at)
+            } else {
+                user_settings.recent_span(start, end)
+            }
+        }
+        None => {
+            let length = parse_duration(when).ok_or_else(invalid)?;
+            if length > Duration::days(1) {
+                return Err(CommandError::Invalid(
+                    "A session cannot be longer than a day.".to_string(),
+                ));
+            }
+            if date != user_settings.current_date() {
+                return Err(CommandError::Invalid(
+                    "A length of time ends now, so it can only be logged for today. Give a start and end time instead.".to_string(),
+                ));
+            }
+            (now - length, now)
+        }
+    };
+
+    if end > now {
+        return Err(CommandError::Invalid(format!(
+            "That session would end in the future, at {}.",
+            end.format("%d/%m/%Y %H:%M")
+        )));
+    }
+
+    let session = Session {
+        id: 0,
+        category,
+        start,
+        end,
+        note: note_from_words(note),
+    };
+    warn_if_overlapping(user_settings, &session);
+    let recorded = update_user_settings(user_settings, |settings| {
+        settings.record_session(session.clone())
+    })??;
+
+    let minutes = session.minutes();
+    println!(
+        "Logged {} {} {} - {} on {} ({} hours and {} minutes).",
+        entry_ids(&recorded),
+        session.category,
+        start.format("%H:%M"),
+        end.format("%H:%M"),
+        filed_dates(user_settings, &recorded),
+        minutes / 60,
+        minutes % 60
+    );
+    Ok(())
+}
+
+pub fn run_entry_command(
+    user_settings: &mut UserSettings,
+    command: EntryCommand,
+) -> Result<(), CommandError> {
+    match command {
+        EntryCommand::List { date } => {
+            let date = resolve_date(user_settings, date.as_deref())?;
+            list_entries(user_settings, date);
+        }
+        EntryCommand::Edit {
+            id,
+            start,
+            end,
+            category,
+            note,
+            date,
+        } => {
+            let move_to = match date {
+                Some(date) => Some(resolve_date(user_settings, Some(&date))?),
+                None => None,
+            };
+            let category = match category {
+                Some(wanted) => Some(
+                    user_settings
+                        .categories
+                        .find(&wanted)
+                        .map(|found| found.name.clone())
+                        .ok_or_else(|| format!("Unknown category '{}'.", wanted))?,
+                ),
+                None => None,
+            };
+            let now = user_settings.now().time();
+            let day_starts_at = user_settings.day_starts_at;
+            let parse = |text: &str| {
+                parse_time_input(text, now)
+                    .ok_or_else(|| format!("'{}' is not a time. Use HH:MM.", text))
+            };
+            let start = start.as_deref().map(parse).transpose()?;
+            let end = end.as_deref().map(parse).transpose()?;
+
+            let edited = update_user_settings(user_settings, |settings| {
+                settings.edit_session(id, move_to, |session, date| {
+                    if start.is_some() || end.is_some() {
+                        (session.start, session.end) = time_span(
+                            date,
+                            start.unwrap_or(session.start.time()),
+                            end.unwrap_or(session.end.time()),
+                            day_starts_at,
+                        );
+                    }
+                    if let Some(category) = category {
+                        session.category = category;
+                    }
+                    if let Some(note) = note {
+                        session.note = note_from_words(&[note]);
+                    }
+                    Ok(())
+                })
+            })??;
+            let (first, last) = (&edited[0], &edited[edited.len() - 1]);
+            let split = if edited.len() > 1 {
+                format!(", split into {}", entry_ids(&edited))
+            } else {
+                String::new()
+            };
+            println!(
+                "Entry #{} is now {} {} - {} on {}{}.",
+                id,
+                first.category,
+                first.start.format("%H:%M"),
+                last.end.format("%H:%M"),
+                filed_dates(user_settings, &edited),
+                split
+            );
+            for part in &edited {
+                warn_if_overlapping(user_settings, part);
+            }
+        }
+        EntryCommand::Delete { id } => {
+            let deleted =
+                update_user_settings(user_settings, |settings| settings.delete_session(id))??;
+            println!(
+                "Deleted #{} {} {} - {}. Run `crawl-todo undo` to bring it back.",
+                deleted.id,
+                deleted.category,
+                deleted.start.format("%H:%M"),
+                deleted.end.format("%H:%M")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn list_entries(user_settings: &UserSettings, date: NaiveDate) {
+    let sessions = user_settings
+        .day(date)
+        .map(|day| day.sessions.as_slice())
+        .unwrap_or_default();
+    if sessions.is_empty() {
+        println!("No entries on {}.", date.format("%d/%m/%Y"));
+        return;
+    }
+    println!("Entries on {}:", date.format("%d/%m/%Y"));
+    for session in sessions {
+        let minutes = session.minutes();
+        println!(
+            "{:>5}  {} - {}  {:>2}h {:02}m  {}{}",
+            format!("#{}", session.id),
+            session.start.format("%H:%M"),
+            session.end.format("%H:%M"),
+            minutes / 60,
+            minutes % 60,
+            user_settings.categories.paint(&session.category),
+            session
+                .note
+                .as_ref()
+                .map(|note| format!("  ({})", note))
+                .unwrap_or_default()
+        );
+    }
+}
+
+pub fn run_undo(user_settings: &mut UserSettings) -> Result<(), CommandError> {
+    let undone = update_user_settings(user_settings, |settings| settings.undo())??;
+    println!(
+        "Undid the change made at {}: {}.",
+        undone.at.format("%d/%m/%Y %H:%M"),
+        undone.summary
+    );
+    if let Some(stopped) = undone.stopped {
+        println!(
+            "The timer for {} is running again since {}.",
+            stopped.category,
+            stopped.start.format("%H:%M")
+        );
+    }
+    Ok(())
+}
+
+pub fn run_day_command(
+    user_settings: &mut UserSettings,
+    command: DayCommand,
+) -> Result<(), CommandError> {
+    match command {
+        DayCommand::Mark { status, dates } => {
+            let status = DayStatus::parse(&status).ok_or_else(|| {
+                format!(
+                    "Unknown day status '{}'. Use tracked, untracked, day-off or holiday.",
+                    status
+                )
+            })?;
+            let dates = if dates.is_empty() {
+                vec![user_settings.current_date()]
+            } else {
+                dates
+                    .iter()
+                    .map(|date| resolve_date(user_settings, Some(date)))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+            update_user_settings(user_settings, |settings| {
+                dates
+                    .iter()
+                    .try_for_each(|date| settings.mark_day(*date, status))
+            })??;
+            for date in dates {
+                println!("{} is marked as {}.", date.format("%d/%m/%Y, %a"), status);
+            }
+        }
+        DayCommand::List { days } => {
+            for day in user_settings.days().take(days) {
+                let minutes = day.total_minutes_spent();
+                println!(
+                    "{}  {:<10} {:>3}h {:02}m",
+                    day.date.format("%d/%m/%Y %a"),
+                    day.status.to_string(),
+                    minutes / 60,
+                    minutes % 60
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run_category_command(
+    user_settings: &mut UserSettings,
+    command: CategoryCommand,
+) -> Result<(), CommandError> {
+    match command {
+        CategoryCommand::List => list_categories(user_settings),
+        CategoryCommand::Add {
+            code,
+            name,
+            class,
+            color,
+        } => {
+            let class = CategoryClass::parse(&class).ok_or_else(|| {
+                format!(
+                    "Unknown class '{}'. Use productive, chore, entertainment or custom.",
+                    class
+                )
+            })?;
+            let category = Category {
+                code: code.clone(),
+                name: name.clone(),
+                class,
+                color,
+                retired: false,
+            };
+            update_user_settings(user_settings, |settings| settings.categories.add(category))??;
+            println!("Added {} ({}) as a {} category.", name, code, class);
+        }
+        CategoryCommand::Rename { code, new_name } => {
+            let old_name = update_user_settings(user_settings, |settings| {
+                let old_name = settings.categories.rename(&code, &new_name)?;
+                settings.rename_category_in_history(&old_name, &new_name);
+                Ok::<_, String>(old_name)
+            })??;
+            println!("Renamed {} to {}.", old_name, new_name);
+        }
+        CategoryCommand::Retire { code } => {
+            let name =
+                update_user_settings(user_settings, |settings| settings.categories.retire(&code))??;
+            println!("Retired {}. Its history is kept.", name);
+        }
+    }
+    Ok(())
+}
+
+pub fn run_goal_command(
+    user_settings: &mut UserSettings,
+    command: GoalCommand,
+) -> Result<(), CommandError> {
+    match command {
+        GoalCommand::List => {
+            if user_settings.goals.is_empty() {
+                println!("No goals set. Add one with `goal add <category> <length>`.");
+            }
+            for (index, goal) in user_settings.goals.all().iter().enumerate() {
+                println!("{:>3}. {}", index + 1, goal);
+            }
+        }
+        GoalCommand::Add {
+            category,
+            length,
+            per,
+            at_most,
+        } => {
+            let category = user_settings
+                .categories
+                .find(&category)
+                .ok_or_else(|| format!("Unknown category '{}'.", category))?
+                .name
+                .clone();
+            let minutes = parse_duration(&length)
+                .ok_or_else(|| {
+                    format!(
+                        "'{}' is not a length of time. Use e.g. 4h, 90m or 1h30.",
+                        length
+                    )
+                })?
+                .num_minutes() as u64;
+            let period = GoalPeriod::parse(&per)
+                .ok_or_else(|| format!("Unknown period '{}'. Use day or week.", per))?;
+            let goal = Goal {
+                category,
+                kind: if at_most {
+                    GoalKind::AtMost
+                } else {
+                    GoalKind::AtLeast
+                },
+                minutes,
+                period,
+            };
+            let description = goal.to_string();
+            match update_user_settings(user_settings, |settings| settings.goals.set(goal))? {
+                Some(replaced) => println!("Replaced {} with {}.", replaced, description),
+                None => println!("Added goal {}.", description),
+            }
+        }
+        GoalCommand::Remove { number } => {
+            let goal =
+                update_user_settings(user_settings, |settings| settings.goals.remove(number))??;
+            println!("Removed goal {}.", goal);
+        }
+    }
+    Ok(())
+}
+
+fn list_categories(user_settings: &UserSettings) {
+    for category in user_settings.categories.all() {
+        let retired = if category.retired { " (retired)" } else { "" };
+        let name = format!("{:<20}", category.name);
+        let name = match category.color.parse::<Color>() {
+            Ok(color) => name.color(color),
+            Err(_) => name.normal(),
+        };
+        println!(
+            "{:<4} {} {:<14} {}{}",
+            category.code,
+            name,
+            category.class.to_string(),
+            category.color,
+            retired
+        );
+    }
+}
+
+pub fn run_todo_command(
+    user_settings: &mut UserSettings,
+    command: TodoCommand,
+) -> Result<(), CommandError> {
+    let now = user_settings.now();
+    let (id, status) = match command {
+        TodoCommand::List { all } => {
+            list_todos(user_settings, all);
+            return Ok(());
+        }
+        TodoCommand::Add { title, category } => {
+            let category = match category {
+                Some(wanted) => Some(
+                    user_settings
+                        .categories
+                        .find(&wanted)
+                        .map(|found| found.name.clone())
+                        .ok_or_else(|| format!("Unknown category '{}'.", wanted))?,
+                ),
+                None => None,
+            };
+            let title = title.join(" ");
+            if title.trim().is_empty() {
+                return Err(CommandError::Invalid(
+                    "A to-do item needs a title.".to_string(),
+                ));
+            }
+            let id = update_user_settings(user_settings, |settings| {
+                settings.todos.add(title.clone(), category, now)
+            })?;
+            println!("Added #{}: {}", id, title);
+            return Ok(());
+        }
+        TodoCommand::Start { id } => (id, TodoStatus::InProgress),
+        TodoCommand::Done { id } => (id, TodoStatus::Done),
+        TodoCommand::Cancel { id } => (id, TodoStatus::Cancelled),
+        TodoCommand::Reopen { id } => (id, TodoStatus::Open),
+    };
+
+    let message = update_user_settings(user_settings, |settings| {
+        settings
+            .todos
+            .set_status(id, status, now)
+            .map(|item| format!("#{} {} is now {}.", item.id, item.title, item.status))
+    })??;
+    println!("{}", message);
+    Ok(())
+}
+
+fn list_todos(user_settings: &UserSettings, include_closed: bool) {
+    let items: Vec<_> = user_settings
+        .todos
+        .items()
+        .iter()
+        .filter(|item| include_closed || item.status.is_open())
+        .collect();
+    if items.is_empty() {
+        println!("Nothing to do.");
+        return;
+    }
+    for item in items {
+        display_todo(user_settings, item);
+    }
+}
+
+const SETTINGS: &str = "end-time, start-time, timezone, day-start, midnight, gaps, backups";
+
+fn optional_time(time: Option<NaiveTime>) -> String {
+    time.map(|time| time.format("%H:%M").to_string())
+        .unwrap_or_else(|| "(not set)".to_string())
+}
+
+fn parse_setting_time(value: &str) -> Result<NaiveTime, String> {
+    parse_time(value).ok_or_else(|| format!("'{}' is not a time. Use HH:MM.", value))
+}
+
+/// Every value is parsed and checked before it is saved, so a bad value is
+/// refused here instead of being stored.
+pub fn run_config_command(
+    user_settings: &mut UserSettings,
+    command: ConfigCommand,
+) -> Result<(), CommandError> {
+    match command {
+        ConfigCommand::Show => {
+            println!("end-time   {}", optional_time(user_settings.end_time));
+            println!(
+                "start-time {}",
+                optional_time(user_settings.default_start_time)
+            );
+            println!("timezone   {}", user_settings.timezone);
+            println!("day-start  {}", user_settings.day_starts_at.format("%H:%M"));
+            println!("midnight   {}", user_settings.midnight_rule);
+            println!("gaps       {}", user_settings.gap_status);
+            println!("backups    {}", user_settings.backup_count);
+        }
+        ConfigCommand::Set { key, value } => match key.as_str() {
+            "end-time" => {
+                let end_time = parse_setting_time(&value)?;
+                update_user_settings(user_settings, |settings| {
+                    settings.end_time = Some(end_time);
+                    settings.reschedule_today();
+                })?;
+                println!("Your day now ends at {}.", end_time.format("%H:%M"));
+            }
+            "start-time" => {
+                let start_time = parse_setting_time(&value)?;
+                update_user_settings(user_settings, |settings| {
+                    settings.default_start_time = Some(start_time);
+                    settings.reschedule_today();
+                })?;
+                println!(
+                    "New days now start at {} without asking.",
+                    start_time.format("%H:%M")
+                );
+            }
+            "timezone" => {
+                let timezone = value.parse::<Timezone>()?;
+                update_user_settings(user_settings, |settings| settings.timezone = timezone)?;
+                println!(
+                    "Timezone set to {}. It is now {}.",
+                    timezone,
+                    user_settings.now().format("%d/%m/%Y %H:%M")
+                );
+            }
+            "backups" => {
+                let count = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("'{}' is not a number of backups.", value))?;
+                update_user_settings(user_settings, |settings| settings.backup_count = count)?;
+                println!("Keeping {} rolling backups.", count);
+            }
+            "midnight" => {
+                let rule = MidnightRule::parse(&value).ok_or_else(|| {
+                    format!(
+                        "Unknown midnight rule '{}'. Use split, start-day or end-day.",
+                        value
+                    )
+                })?;
+                update_user_settings(user_settings, |settings| settings.midnight_rule = rule)?;
+                println!(
+                    "Sessions that run past midnight will use the {} rule.",
+                    rule
+                );
+            }
+            "day-start" => {
+                let day_starts_at = parse_setting_time(&value)?;
+                update_user_settings(user_settings, |settings| {
+                    settings.day_starts_at = day_starts_at
+                })?;
+                println!(
+                    "Days now start at {}. Days already recorded keep their dates.",
+                    day_starts_at.format("%H:%M")
+                );
+            }
+            "gaps" => {
+                let status = DayStatus::parse(&value)
+                    .filter(|status| !status.is_tracked())
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown gap status '{}'. Use untracked, day-off or holiday.",
+                            value
+                        )
+                    })?;
+                update_user_settings(user_settings, |settings| settings.gap_status = status)?;
+                println!("Days skipped between runs will be marked as {}.", status);
+            }
+            _ => {
+                return Err(CommandError::Invalid(format!(
+                    "Unknown setting '{}'. Known settings: {}.",
+                    key, SETTINGS
+                )))
+            }
+        },
+        ConfigCommand::Unset { key } => match key.as_str() {
+            "end-time" => {
+                update_user_settings(user_settings, |settings| {
+                    settings.end_time = None;
+                    settings.reschedule_today();
+                })?;
+                println!("Your day has no set end time.");
+            }
+            "start-time" => {
+                update_user_settings(user_settings, |settings| {
+                    settings.default_start_time = None;
+                    settings.reschedule_today();
+                })?;
+                println!("You will be asked for each day's start time.");
+            }
+            _ => {
+                return Err(CommandError::Invalid(format!(
+                    "Only end-time and start-time can be unset; use `config set` for '{}'.",
+                    key
+                )))
+            }
+        },
+    }
+    Ok(())
+}
+
+pub fn run_schedule_command(
+    user_settings: &mut UserSettings,
+    command: ScheduleCommand,
+) -> Result<(), CommandError> {
+    let (days, scheduled_day) = match command {
+        ScheduleCommand::Show => {
+            for weekday in WEEK {
+                let hours = match user_settings.schedule.get(weekday) {
+                    Some(scheduled_day) => scheduled_day.to_string(),
+                    None => format!(
+                        "default (start {}, end {})",
+                        optional_time(user_settings.default_start_time),
+                        optional_time(user_settings.end_time)
+                    ),
+                };
+                println!("{}  {}", weekday, hours);
+            }
+            return Ok(());
+        }
+        ScheduleCommand::Set { days, hours } => (days, Some(hours.parse::<ScheduledDay>()?)),
+        ScheduleCommand::Clear { days } => (days, None),
+    };
+
+    let weekdays = parse_weekdays(&days).ok_or_else(|| {
+        format!(
+            "'{}' is not a set of weekdays. Use e.g. fri, mon-thu, sat,sun, weekdays or all.",
+            days
+        )
+    })?;
+    update_user_settings(user_settings, |settings| {
+        for weekday in &weekdays {
+            settings.schedule.set(*weekday, scheduled_day);
+        }
+        settings.reschedule_today();
+    })?;
+    let weekdays = weekdays
+        .iter()
+        .map(|weekday| weekday.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    match scheduled_day {
+        Some(ScheduledDay::Off) => println!("{}: not a working day.", weekdays),
+        Some(hours) => println!("{}: working {}.", weekdays, hours),
+        None => println!("{}: using the default start and end times.", weekdays),
+    }
+    Ok(())
+}
+
+pub fn run_migrate_command(dry_run: bool) -> Result<(), CommandError> {
+    if !dry_run {
+        let user_settings = load_user_settings()?;
+        println!("The settings file is at version {}.", user_settings.version);
+        return Ok(());
+    }
+
+    let (from_version, changes) = preview_migration()?;
+    if from_version == CURRENT_VERSION {
+        println!(
+            "The settings file is already at version {}. Nothing to do.",
+            CURRENT_VERSION
+        );
+    } else {
+        println!(
+            "Migrating would upgrade the settings file from version {} to {}:",
+            from_version, CURRENT_VERSION
+        );
+        for change in changes {
+            println!("  {}", change);
+        }
+        println!("Nothing was written. Run `crawl-todo migrate` to apply.");
+    }
+    Ok(())
+}
+
+pub fn run_restore_command(number: Option<usize>) -> Result<(), CommandError> {
+    let backups = list_backups()?;
+    if backups.is_empty() {
+        println!("There are no backups yet.");
+        return Ok(());
+    }
+
+    let Some(number) = number else {
+        println!("Backups, newest first:");
+        for (index, path) in backups.iter().enumerate() {
+            let size = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            println!(
+                "{:>3}  {}  ({} bytes)",
+                index + 1,
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                size
+            );
+        }
+        println!("Run `crawl-todo restore <number>` to restore one.");
+        return Ok(());
+    };
+
+    if !(1..=backups.len()).contains(&number) {
+        return Err(CommandError::Invalid(format!(
+            "Choose a backup number between 1 and {}.",
+            backups.len()
+        )));
+    }
+    let backup_path = &backups[number - 1];
+    restore_backup(backup_path)?;
+    println!("{}", format!("Restored {}.", backup_path.display()).green());
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use crate::clock::format_duration;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Whether a goal asks for at least or at most its amount of time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GoalKind {
+    AtLeast,
+    AtMost,
+}
+
+/// The period a goal's time is counted over. Weeks start on Monday.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GoalPeriod {
+    Day,
+    Week,
+}
+
+impl GoalPeriod {
+    pub fn parse(input: &str) -> Option<GoalPeriod> {
+        match input.to_lowercase().as_str() {
+            "day" | "daily" | "d" => Some(GoalPeriod::Day),
+            "week" | "weekly" | "w" => Some(GoalPeriod::Week),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GoalPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GoalPeriod::Day => "day",
+            GoalPeriod::Week => "week",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How a goal stands for one day or week.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoalOutcome {
+    Met,
+    Missed,
+    /// The period is still running and could go either way.
+    Pending,
+    /// Nothing was tracked in the period, so it does not count.
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Goal {
+    pub category: String,
+    pub kind: GoalKind,
+    pub minutes: u64,
+    pub period: GoalPeriod,
+}
+
+impl Goal {
+    /// Time spent as a percentage of the goal's amount.
+    pub fn progress(&self, minutes_spent: u64) -> u64 {
+        minutes_spent * 100 / self.minutes.max(1)
+    }
+
+    /// Judges the time spent in one period. An unfinished period is only
+    /// decided once more time could no longer change the result.
+    pub fn outcome(&self, minutes_spent: u64, finished: bool) -> GoalOutcome {
+        match self.kind {
+            GoalKind::AtLeast if minutes_spent >= self.minutes => GoalOutcome::Met,
+            GoalKind::AtMost if minutes_spent > self.minutes => GoalOutcome::Missed,
+            _ if !finished => GoalOutcome::Pending,
+            GoalKind::AtLeast => GoalOutcome::Missed,
+            GoalKind::AtMost => GoalOutcome::Met,
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.kind {
+            GoalKind::AtLeast => "≥",
+            GoalKind::AtMost => "≤",
+        };
+        write!(
+            f,
+            "{} {} {}/{}",
+            self.category,
+            sign,
+            format_duration(self.minutes),
+            self.period
+        )
+    }
+}
+
+/// The user's goals, numbered from 1 in the order they were added.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Goals(Vec<Goal>);
+
+impl Goals {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn all(&self) -> &[Goal] {
+        &self.0
+    }
+
+    /// Adds a goal, replacing an existing one of the same kind for the same
+    /// category and period. Returns the goal it replaced.
+    pub fn set(&mut self, goal: Goal) -> Option<Goal> {
+        match self.0.iter_mut().find(|existing| {
+            existing.category == goal.category
+                && existing.kind == goal.kind
+                && existing.period == goal.period
+        }) {
+            Some(existing) => Some(std::mem::replace(existing, goal)),
+            None => {
+                self.0.push(goal);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, number: usize) -> Result<Goal, String> {
+        if number == 0 || number > self.0.len() {
+            return Err(format!("There is no goal number {}.", number));
+        }
+        Ok(self.0.remove(number - 1))
+    }
+
+    pub fn rename_category(&mut self, old_name: &str, new_name: &str) {
+        for goal in &mut self.0 {
+            if goal.category == old_name {
+                goal.category = new_name.to_string();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(kind: GoalKind) -> Goal {
+        Goal {
+            category: "Coding".to_string(),
+            kind,
+            minutes: 240,
+            period: GoalPeriod::Day,
+        }
+    }
+
+    #[test]
+    fn at_least_is_met_as_soon_as_it_is_reached() {
+        let goal = goal(GoalKind::AtLeast);
+        assert_eq!(goal.outcome(240, false), GoalOutcome::Met);
+        assert_eq!(goal.outcome(239, false), GoalOutcome::Pending);
+        assert_eq!(goal.outcome(239, true), GoalOutcome::Missed);
+        assert_eq!(goal.outcome(300, true), GoalOutcome::Met);
+    }
+
+    #[test]
+    fn at_most_is_missed_as_soon_as_it_is_passed() {
+        let goal = goal(GoalKind::AtMost);
+        assert_eq!(goal.outcome(241, false), GoalOutcome::Missed);
+        assert_eq!(goal.outcome(240, false), GoalOutcome::Pending);
+        assert_eq!(goal.outcome(240, true), GoalOutcome::Met);
+        assert_eq!(goal.outcome(0, true), GoalOutcome::Met);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::task::{ActiveSession, Session};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// How many changes `undo` can walk back.
+const JOURNAL_LENGTH: usize = 50;
+
+/// A session together with the day it is filed under.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DatedSession {
+    pub date: NaiveDate,
+    pub session: Session,
+}
+
+/// One change to the logged sessions. Undoing it removes `added` and puts
+/// `removed` back, so an edit is recorded as both. A change made by stopping
+/// a timer keeps the timer in `stopped` so that undo can restart it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub at: NaiveDateTime,
+    pub summary: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<DatedSession>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<DatedSession>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<ActiveSession>,
+}
+
+/// The most recent changes to logged sessions, oldest first. It is stored in
+/// the settings file so that `undo` works across runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Journal(Vec<JournalEntry>);
+
+impl Journal {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.0.push(entry);
+        if self.0.len() > JOURNAL_LENGTH {
+            let excess = self.0.len() - JOURNAL_LENGTH;
+            self.0.drain(..excess);
+        }
+    }
+
+    /// Keeps undone sessions under a renamed category's new name.
+    pub fn rename_category(&mut self, old_name: &str, new_name: &str) {
+        for entry in &mut self.0 {
+            for dated in entry.added.iter_mut().chain(entry.removed.iter_mut()) {
+                if dated.session.category == old_name {
+                    dated.session.category = new_name.to_string();
+                }
+            }
+        }
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut JournalEntry> {
+        self.0.last_mut()
+    }
+
+    pub fn pop(&mut self) -> Option<JournalEntry> {
+        self.0.pop()
+    }
+}
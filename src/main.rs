@@ -1,56 +1,128 @@
+mod category;
+mod chart;
+mod cli;
+mod clock;
+mod commands;
+mod goal;
+mod journal;
+mod migration;
+mod schedule;
 mod serialization;
 mod stats;
 mod task;
+mod todo;
 mod user_interaction;
-use crate::serialization::{load_user_settings, save_user_settings};
-use crate::stats::display_stats;
-use crate::task::TodaysTasks;
-use crate::user_interaction::{display_summary, prompt_task};
-use chrono::{Duration, Utc};
-use std::env;
+use crate::cli::{Cli, Command};
+use crate::commands::{
+    run_category_command, run_config_command, run_day_command, run_entry_command, run_goal_command,
+    run_interactive, run_log, run_migrate_command, run_report, run_restore_command,
+    run_schedule_command, run_start, run_status, run_stop, run_todo_command, run_undo,
+    CommandError,
+};
+use crate::serialization::{load_user_settings, update_user_settings};
+use crate::stats::{display_stats, ReportOptions, SortOrder};
+use crate::user_interaction::offer_backup_restore;
+use clap::Parser;
+use colored::*;
+use std::process::ExitCode;
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let period = if args.len() > 1 { &args[1] } else { "daily" };
-    let mut user_settings = load_user_settings();
-    let current_time = Utc::now() + Duration::hours(2);
-    let formatted_date = current_time.format("%d/%m/%Y").to_string();
-
-    if user_settings.today.date != formatted_date {
-        user_settings.past_tasks.push(user_settings.today.clone());
-        user_settings.today = TodaysTasks::new(
-            formatted_date.clone(),
-            Some(current_time.format("%H:%M:%S").to_string()),
-        );
-        save_user_settings(&user_settings);
-    }
-
-    match period {
-        "weekly" | "monthly" => {
-            display_stats(&user_settings, period);
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Interactive);
+    match run(&command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", error.to_string().red());
+            if error.is_corrupt_file() && recover(&command) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
         }
-        "daily" | _ => {
-            let start_time = user_settings.get_start_time();
-            let end_of_day = user_settings.get_end_time(&current_time);
-            let day_duration = end_of_day.time().signed_duration_since(start_time);
-            let total_hours = day_duration.num_hours();
-            let total_minutes = day_duration.num_minutes() % 60;
+    }
+}
 
-            println!(
-                "{} hours and {} minutes to seize the day",
-                total_hours, total_minutes
-            );
+/// Offers to restore the latest backup of a corrupt settings file and, if the
+/// user accepts, runs the command again.
+fn recover(command: &Command) -> bool {
+    let retried = offer_backup_restore()
+        .map_err(CommandError::from)
+        .and_then(|restored| {
+            if restored {
+                run(command).map(|()| true)
+            } else {
+                Ok(false)
+            }
+        });
+    retried.unwrap_or_else(|error| {
+        eprintln!("{}", error.to_string().red());
+        false
+    })
+}
 
-            display_summary(&mut user_settings, &current_time, end_of_day);
+fn run(command: &Command) -> Result<(), CommandError> {
+    // These work on the file itself and must run even if it cannot be loaded.
+    match command {
+        Command::Migrate { dry_run } => return run_migrate_command(*dry_run),
+        Command::Restore { number } => return run_restore_command(*number),
+        _ => {}
+    }
 
-            loop {
-                if !prompt_task(&mut user_settings) {
-                    break;
-                }
-                save_user_settings(&user_settings);
-            }
+    let mut user_settings = load_user_settings()?;
+    let current_time = user_settings.now();
+    if user_settings.today.date < user_settings.logical_date(current_time) {
+        update_user_settings(&mut user_settings, |settings| {
+            settings.roll_over(current_time)
+        })?;
+    }
 
-            display_stats(&user_settings, "daily");
+    match command.clone() {
+        Command::Interactive => run_interactive(&mut user_settings)?,
+        Command::Status => run_status(&user_settings),
+        Command::Start { category, note } => run_start(&mut user_settings, &category, &note)?,
+        Command::Stop { note } => run_stop(&mut user_settings, &note)?,
+        Command::Log {
+            category,
+            when,
+            note,
+            date,
+        } => run_log(&mut user_settings, &category, &when, &note, date.as_deref())?,
+        Command::Entry(entry_command) => run_entry_command(&mut user_settings, entry_command)?,
+        Command::Undo => run_undo(&mut user_settings)?,
+        Command::Day(day_command) => run_day_command(&mut user_settings, day_command)?,
+        Command::Report {
+            period,
+            from,
+            to,
+            sort,
+            chart,
+            sparkline_days,
+        } => run_report(
+            &user_settings,
+            &period,
+            from.as_deref(),
+            to.as_deref(),
+            ReportOptions {
+                sort: SortOrder::parse(&sort).ok_or_else(|| {
+                    format!("Unknown sort order '{}'. Use time, name or class.", sort)
+                })?,
+                chart,
+                sparkline_days: sparkline_days.into(),
+            },
+        )?,
+        Command::Daily => display_stats(&user_settings, "daily", &ReportOptions::default())?,
+        Command::Weekly => display_stats(&user_settings, "weekly", &ReportOptions::default())?,
+        Command::Monthly => display_stats(&user_settings, "monthly", &ReportOptions::default())?,
+        Command::Todo(todo_command) => run_todo_command(&mut user_settings, todo_command)?,
+        Command::Category(category_command) => {
+            run_category_command(&mut user_settings, category_command)?
+        }
+        Command::Goal(goal_command) => run_goal_command(&mut user_settings, goal_command)?,
+        Command::Config(config_command) => run_config_command(&mut user_settings, config_command)?,
+        Command::Schedule(schedule_command) => {
+            run_schedule_command(&mut user_settings, schedule_command)?
         }
+        Command::Migrate { .. } | Command::Restore { .. } => unreachable!(),
     }
+    Ok(())
 }
@@ -0,0 +1,232 @@
+use crate::clock::{parse_date, parse_time};
+use serde_json::{Map, Value};
+
+/// Version written by this build. Bump it together with a new entry in
+/// `MIGRATIONS` whenever the stored shape changes.
+pub const CURRENT_VERSION: u32 = 2;
+
+type Migration = fn(&mut Map<String, Value>) -> Vec<String>;
+
+/// `MIGRATIONS[n]` upgrades a document from version `n` to `n + 1` and
+/// describes what it changed.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [migrate_v0_to_v1, migrate_v1_to_v2];
+
+pub fn document_version(document: &Value) -> u32 {
+    document.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+/// Upgrades a settings document in place, one version at a time, and returns a
+/// description of every change made along the way.
+pub fn migrate(document: &mut Value) -> Result<Vec<String>, String> {
+    let version = document_version(document);
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "The settings file was written by a newer version of crawl-todo (schema {}, this build understands up to {}).",
+            version, CURRENT_VERSION
+        ));
+    }
+    let object = document
+        .as_object_mut()
+        .ok_or_else(|| "The settings file does not contain a JSON object.".to_string())?;
+
+    let mut changes = Vec::new();
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        for change in migration(object) {
+            changes.push(format!("v{} -> v{}: {}", from, from + 1, change));
+        }
+        object.insert("version".to_string(), Value::from(from as u32 + 1));
+    }
+    Ok(changes)
+}
+
+/// Version 0 files have no `version` field and store dates as `%d/%m/%Y` and
+/// times as `%H:%M` or `%H:%M:%S` strings.
+fn migrate_v0_to_v1(document: &mut Map<String, Value>) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    if let Some(end_time) = document.get_mut("end_time") {
+        rewrite_time(end_time, "end_time", &mut changes);
+    }
+    if let Some(today) = document.get_mut("today") {
+        rewrite_day(today, &mut changes);
+    }
+    if let Some(Value::Array(past_tasks)) = document.get_mut("past_tasks") {
+        for day in past_tasks {
+            rewrite_day(day, &mut changes);
+        }
+    }
+
+    changes.push("added schema version field".to_string());
+    changes
+}
+
+/// Version 2 gives every session an id so it can be edited or deleted.
+fn migrate_v1_to_v2(document: &mut Map<String, Value>) -> Vec<String> {
+    let mut next_id: u32 = 1;
+    let mut number_sessions = |day: &mut Value| {
+        if let Some(Value::Array(sessions)) = day.get_mut("sessions") {
+            for session in sessions.iter_mut().filter_map(Value::as_object_mut) {
+                session.insert("id".to_string(), Value::from(next_id));
+                next_id += 1;
+            }
+        }
+    };
+    if let Some(Value::Array(past_tasks)) = document.get_mut("past_tasks") {
+        past_tasks.iter_mut().for_each(&mut number_sessions);
+    }
+    if let Some(today) = document.get_mut("today") {
+        number_sessions(today);
+    }
+
+    document.insert("next_session_id".to_string(), Value::from(next_id));
+    vec![format!("numbered {} sessions", next_id - 1)]
+}
+
+fn rewrite_day(day: &mut Value, changes: &mut Vec<String>) {
+    let Some(day) = day.as_object_mut() else {
+        return;
+    };
+    if let Some(date) = day.get_mut("date") {
+        if let Some(text) = date.as_str() {
+            if let Some(parsed) = parse_date(text) {
+                let iso = parsed.format("%Y-%m-%d").to_string();
+                if text != iso {
+                    changes.push(format!("date \"{}\" -> \"{}\"", text, iso));
+                    *date = Value::from(iso);
+                }
+            }
+        }
+    }
+    let label = day
+        .get("date")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown day")
+        .to_string();
+    if let Some(start_time) = day.get_mut("start_time") {
+        rewrite_time(start_time, &format!("start_time of {}", label), changes);
+    }
+}
+
+fn rewrite_time(time: &mut Value, label: &str, changes: &mut Vec<String>) {
+    let Some(text) = time.as_str() else {
+        return;
+    };
+    match parse_time(text) {
+        Some(parsed) => {
+            let iso = parsed.format("%H:%M:%S").to_string();
+            if text != iso {
+                changes.push(format!("{} \"{}\" -> \"{}\"", label, text, iso));
+                *time = Value::from(iso);
+            }
+        }
+        None => {
+            changes.push(format!(
+                "{} \"{}\" is not a time and was cleared",
+                label, text
+            ));
+            *time = Value::Null;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::UserSettings;
+    use chrono::{NaiveDate, NaiveTime};
+    use serde_json::json;
+
+    fn legacy_document() -> Value {
+        json!({
+            "end_time": "18:00",
+            "today": {
+                "date": "18/10/2026",
+                "start_time": "Unknown",
+                "todays_tasks": { "Coding": { "minutes_spent": 30 } },
+                "todays_chores": {},
+                "todays_entertainment": {},
+                "sessions": [
+                    { "category": "Coding", "start": "2026-10-18T09:00:00", "end": "2026-10-18T09:30:00" }
+                ]
+            },
+            "past_tasks": [
+                {
+                    "date": "16/10/2026",
+                    "start_time": "08:30",
+                    "todays_tasks": { "Coding": { "minutes_spent": 60 } },
+                    "todays_chores": {},
+                    "todays_entertainment": {}
+                },
+                {
+                    "date": "17/10/2026",
+                    "start_time": "09:15:00",
+                    "todays_tasks": { "Reading": { "minutes_spent": 90 } },
+                    "todays_chores": {},
+                    "todays_entertainment": {},
+                    "sessions": [
+                        { "category": "Reading", "start": "2026-10-17T10:00:00", "end": "2026-10-17T11:00:00" },
+                        { "category": "Reading", "start": "2026-10-17T12:00:00", "end": "2026-10-17T12:30:00" }
+                    ]
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn legacy_file_migrates_to_current_version() {
+        let mut document = legacy_document();
+        let changes = migrate(&mut document).unwrap();
+        assert_eq!(document_version(&document), CURRENT_VERSION);
+        assert!(changes.iter().any(|change| change.starts_with("v0 -> v1")));
+        assert!(changes.iter().any(|change| change.starts_with("v1 -> v2")));
+
+        assert_eq!(document["end_time"], "18:00:00");
+        assert_eq!(document["today"]["date"], "2026-10-18");
+        assert_eq!(document["today"]["start_time"], Value::Null);
+        assert_eq!(document["past_tasks"][0]["date"], "2026-10-16");
+        assert_eq!(document["past_tasks"][0]["start_time"], "08:30:00");
+        assert_eq!(document["past_tasks"][1]["start_time"], "09:15:00");
+
+        let settings: UserSettings = serde_json::from_value(document).unwrap();
+        assert_eq!(
+            settings.today.date,
+            NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()
+        );
+        assert_eq!(settings.today.start_time, None);
+        assert_eq!(
+            settings.end_time,
+            Some(NaiveTime::from_hms_opt(18, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn sessions_are_numbered_oldest_first() {
+        let mut document = legacy_document();
+        migrate(&mut document).unwrap();
+        let ids = |day: &Value| -> Vec<u64> {
+            day["sessions"]
+                .as_array()
+                .map(|sessions| sessions.iter().map(|s| s["id"].as_u64().unwrap()).collect())
+                .unwrap_or_default()
+        };
+        assert_eq!(ids(&document["past_tasks"][0]), Vec::<u64>::new());
+        assert_eq!(ids(&document["past_tasks"][1]), vec![1, 2]);
+        assert_eq!(ids(&document["today"]), vec![3]);
+        assert_eq!(document["next_session_id"], 4);
+    }
+
+    #[test]
+    fn current_file_is_left_alone() {
+        let mut document = legacy_document();
+        migrate(&mut document).unwrap();
+        let migrated = document.clone();
+        assert!(migrate(&mut document).unwrap().is_empty());
+        assert_eq!(document, migrated);
+    }
+
+    #[test]
+    fn newer_file_is_refused() {
+        let mut document = json!({ "version": CURRENT_VERSION + 1 });
+        assert!(migrate(&mut document).is_err());
+    }
+}
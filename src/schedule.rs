@@ -0,0 +1,201 @@
+use crate::clock::parse_time;
+use chrono::{Duration, NaiveTime, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// What one weekday looks like in the work schedule.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScheduledDay {
+    Off,
+    Hours { start: NaiveTime, end: NaiveTime },
+}
+
+impl ScheduledDay {
+    /// Scheduled working time. Hours that end before they start run past
+    /// midnight.
+    pub fn minutes(self) -> u64 {
+        match self {
+            ScheduledDay::Off => 0,
+            ScheduledDay::Hours { start, end } => {
+                let mut length = end - start;
+                if length < Duration::zero() {
+                    length += Duration::days(1);
+                }
+                length.num_minutes() as u64
+            }
+        }
+    }
+}
+
+impl FromStr for ScheduledDay {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if input.eq_ignore_ascii_case("off") {
+            return Ok(ScheduledDay::Off);
+        }
+        input
+            .split_once('-')
+            .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+            .filter(|(start, end)| start != end)
+            .map(|(start, end)| ScheduledDay::Hours { start, end })
+            .ok_or_else(|| {
+                format!(
+                    "'{}' is not a working day. Use hours like 09:00-17:00, or 'off'.",
+                    input
+                )
+            })
+    }
+}
+
+impl fmt::Display for ScheduledDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduledDay::Off => write!(f, "off"),
+            ScheduledDay::Hours { start, end } => {
+                write!(f, "{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+            }
+        }
+    }
+}
+
+impl Serialize for ScheduledDay {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScheduledDay {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Working hours per weekday. Weekdays without an entry fall back to the
+/// `end-time` and `start-time` settings.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WorkSchedule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mon: Option<ScheduledDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tue: Option<ScheduledDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wed: Option<ScheduledDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thu: Option<ScheduledDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fri: Option<ScheduledDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sat: Option<ScheduledDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sun: Option<ScheduledDay>,
+}
+
+impl WorkSchedule {
+    pub fn is_empty(&self) -> bool {
+        WEEK.iter().all(|weekday| self.get(*weekday).is_none())
+    }
+
+    pub fn get(&self, weekday: Weekday) -> Option<ScheduledDay> {
+        match weekday {
+            Weekday::Mon => self.mon,
+            Weekday::Tue => self.tue,
+            Weekday::Wed => self.wed,
+            Weekday::Thu => self.thu,
+            Weekday::Fri => self.fri,
+            Weekday::Sat => self.sat,
+            Weekday::Sun => self.sun,
+        }
+    }
+
+    pub fn set(&mut self, weekday: Weekday, day: Option<ScheduledDay>) {
+        let slot = match weekday {
+            Weekday::Mon => &mut self.mon,
+            Weekday::Tue => &mut self.tue,
+            Weekday::Wed => &mut self.wed,
+            Weekday::Thu => &mut self.thu,
+            Weekday::Fri => &mut self.fri,
+            Weekday::Sat => &mut self.sat,
+            Weekday::Sun => &mut self.sun,
+        };
+        *slot = day;
+    }
+}
+
+pub const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Parses the days a schedule change applies to: a weekday (`fri`), a range
+/// (`mon-thu`), a comma-separated list of those, or `weekdays`, `weekend` or
+/// `all`.
+pub fn parse_weekdays(input: &str) -> Option<Vec<Weekday>> {
+    let mut weekdays = Vec::new();
+    for part in input.to_lowercase().split(',') {
+        match part.trim() {
+            "weekdays" => weekdays.extend(&WEEK[..5]),
+            "weekend" => weekdays.extend(&WEEK[5..]),
+            "all" => weekdays.extend(&WEEK),
+            part => match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (first.parse::<Weekday>().ok()?, last.parse().ok()?);
+                    let mut weekday = first;
+                    weekdays.push(weekday);
+                    while weekday != last {
+                        weekday = weekday.succ();
+                        weekdays.push(weekday);
+                    }
+                }
+                None => weekdays.push(part.parse().ok()?),
+            },
+        }
+    }
+    weekdays.sort_by_key(Weekday::num_days_from_monday);
+    weekdays.dedup();
+    Some(weekdays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_weekday_sets() {
+        use Weekday::*;
+        assert_eq!(parse_weekdays("fri"), Some(vec![Fri]));
+        assert_eq!(parse_weekdays("mon-thu"), Some(vec![Mon, Tue, Wed, Thu]));
+        assert_eq!(parse_weekdays("Sun,sat"), Some(vec![Sat, Sun]));
+        assert_eq!(parse_weekdays("weekend"), Some(vec![Sat, Sun]));
+        assert_eq!(parse_weekdays("weekdays,fri"), Some(WEEK[..5].to_vec()));
+        assert_eq!(parse_weekdays("all"), Some(WEEK.to_vec()));
+        // A range may wrap around the end of the week.
+        assert_eq!(parse_weekdays("fri-mon"), Some(vec![Mon, Fri, Sat, Sun]));
+    }
+
+    #[test]
+    fn rejects_unknown_weekdays() {
+        for input in ["", "someday", "mon-", "mon,,tue", "mon-xyz"] {
+            assert_eq!(parse_weekdays(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn scheduled_day_minutes() {
+        let day: ScheduledDay = "09:00-17:30".parse().unwrap();
+        assert_eq!(day.minutes(), 510);
+        let night: ScheduledDay = "22:00-02:00".parse().unwrap();
+        assert_eq!(night.minutes(), 240);
+        assert_eq!("off".parse::<ScheduledDay>(), Ok(ScheduledDay::Off));
+        assert!("09:00-09:00".parse::<ScheduledDay>().is_err());
+        assert!("9-5".parse::<ScheduledDay>().is_err());
+    }
+}
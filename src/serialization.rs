@@ -1,46 +1,461 @@
+use crate::clock::{parse_date, parse_time, Timezone};
+use crate::migration::{document_version, migrate, CURRENT_VERSION};
 use crate::task::UserSettings;
-use chrono::Utc;
+use chrono::{Local, NaiveDate, NaiveTime};
 use dirs::home_dir;
-use serde_json;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::fmt;
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufReader, BufWriter};
-use std::path::PathBuf;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-fn get_config_path() -> PathBuf {
-    let mut path = home_dir().expect("Unable to find home directory");
+#[derive(Debug)]
+pub enum StorageError {
+    NoHomeDirectory,
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Migration {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl StorageError {
+    fn io(action: &'static str, path: &Path) -> impl FnOnce(io::Error) -> StorageError {
+        let path = path.to_path_buf();
+        move |source| StorageError::Io {
+            action,
+            path,
+            source,
+        }
+    }
+
+    fn parse(path: &Path, error: serde_json::Error) -> StorageError {
+        let position = format!(" at line {} column {}", error.line(), error.column());
+        let message = error.to_string();
+        StorageError::Parse {
+            path: path.to_path_buf(),
+            line: error.line(),
+            column: error.column(),
+            message: message
+                .strip_suffix(&position)
+                .unwrap_or(&message)
+                .to_string(),
+        }
+    }
+
+    /// Whether the settings file exists but its contents cannot be used.
+    pub fn is_corrupt_file(&self) -> bool {
+        matches!(self, StorageError::Parse { .. })
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NoHomeDirectory => {
+                write!(
+                    f,
+                    "Unable to find your home directory to store settings in."
+                )
+            }
+            StorageError::Io {
+                action,
+                path,
+                source,
+            } => write!(f, "Unable to {} {}: {}", action, path.display(), source),
+            StorageError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } if *line > 0 => write!(
+                f,
+                "{} is not valid (line {}, column {}): {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            StorageError::Parse { path, message, .. } => {
+                write!(f, "{} is not valid: {}", path.display(), message)
+            }
+            StorageError::Migration { path, message } => {
+                write!(f, "Unable to upgrade {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+fn get_config_dir() -> Result<PathBuf, StorageError> {
+    let mut path = home_dir().ok_or(StorageError::NoHomeDirectory)?;
     path.push(".config");
     path.push("crawl-todo");
-    path.push("user_settings.json");
-    path
+    Ok(path)
+}
+
+fn get_config_path() -> Result<PathBuf, StorageError> {
+    Ok(get_config_dir()?.join("user_settings.json"))
+}
+
+fn get_backup_dir() -> Result<PathBuf, StorageError> {
+    Ok(get_config_dir()?.join("backups"))
 }
 
-fn ensure_config_dir() {
-    let mut dir = get_config_path();
-    dir.pop(); // Remove the filename to get the directory
-    fs::create_dir_all(dir).expect("Unable to create config directory");
+fn ensure_config_dir() -> Result<(), StorageError> {
+    let dir = get_config_dir()?;
+    fs::create_dir_all(&dir).map_err(StorageError::io("create", &dir))
 }
 
-pub fn load_user_settings() -> UserSettings {
-    ensure_config_dir();
-    let path = get_config_path();
-    let file = File::open(&path).unwrap_or_else(|_| {
-        let default_settings = UserSettings::new(Utc::now().format("%d/%m/%Y").to_string());
-        save_user_settings(&default_settings);
-        File::open(&path).expect("Unable to create settings file")
-    });
-    let reader = BufReader::new(file);
-    serde_json::from_reader(reader).expect("Unable to parse settings file")
+fn read_settings_text(path: &Path) -> Result<String, StorageError> {
+    fs::read_to_string(path).map_err(StorageError::io("read", path))
 }
 
-pub fn save_user_settings(user_settings: &UserSettings) {
-    ensure_config_dir();
-    let path = get_config_path();
+/// Set once this run has a backup of the settings file as it was before the
+/// first write, so that saving does not take another.
+static BACKED_UP: AtomicBool = AtomicBool::new(false);
+
+/// Copies the settings file into the backup directory under a timestamped
+/// name, with a counter added when a backup of the same kind was already
+/// taken that second. Labelled backups are made before migrations and
+/// restores and are never pruned; unlabelled ones are the rolling backups
+/// taken on save.
+fn backup_settings_file(label: Option<&str>) -> Result<PathBuf, StorageError> {
+    let backup_dir = get_backup_dir()?;
+    fs::create_dir_all(&backup_dir).map_err(StorageError::io("create", &backup_dir))?;
+    let timestamp = Local::now().format("%Y%m%dT%H%M%S").to_string();
+    let name = |count: u32| {
+        let stamp = match count {
+            1 => timestamp.clone(),
+            _ => format!("{}_{}", timestamp, count),
+        };
+        match label {
+            Some(label) => format!("user_settings-{}-{}.json", stamp, label),
+            None => format!("user_settings-{}.json", stamp),
+        }
+    };
+    // Writers hold the settings lock, so no one else can take the name.
+    let backup_path = (1..)
+        .map(|count| backup_dir.join(name(count)))
+        .find(|path| !path.exists())
+        .expect("some backup name is free");
+    let path = get_config_path()?;
+    fs::copy(&path, &backup_path).map_err(StorageError::io("back up", &path))?;
+    BACKED_UP.store(true, Ordering::SeqCst);
+    Ok(backup_path)
+}
+
+fn is_rolling_backup(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_prefix("user_settings-"))
+        .is_some_and(|timestamp| !timestamp.contains('-'))
+}
+
+/// Deletes the oldest rolling backups so that at most `keep` remain.
+fn prune_backups(keep: usize) -> Result<(), StorageError> {
+    let rolling: Vec<PathBuf> = list_backups()?
+        .into_iter()
+        .filter(|path| is_rolling_backup(path))
+        .collect();
+    for path in rolling.iter().skip(keep) {
+        fs::remove_file(path).map_err(StorageError::io("remove", path))?;
+    }
+    Ok(())
+}
+
+/// Writes to a temporary file next to `path`, flushes it to disk and renames
+/// it over the original, so a crash leaves either the old or the new file.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), StorageError> {
+    let temp_path = path.with_extension("json.tmp");
     let file = OpenOptions::new()
         .write(true)
         .truncate(true)
         .create(true)
+        .open(&temp_path)
+        .map_err(StorageError::io("open", &temp_path))?;
+    let mut writer = BufWriter::new(file);
+    writer
+        .write_all(contents)
+        .and_then(|()| writer.flush())
+        .map_err(StorageError::io("write", &temp_path))?;
+    writer
+        .get_ref()
+        .sync_all()
+        .map_err(StorageError::io("sync", &temp_path))?;
+    fs::rename(&temp_path, path).map_err(StorageError::io("replace", path))?;
+    // Persist the rename itself. Not every platform can open a directory, so
+    // failing to do so is not treated as an error.
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+/// Takes an exclusive advisory lock that serializes writers across processes.
+/// The lock is released when the returned file is dropped.
+fn lock_settings() -> Result<File, StorageError> {
+    let path = get_config_dir()?.join("user_settings.lock");
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
         .open(&path)
-        .expect("Unable to open settings file for writing");
-    let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, user_settings).expect("Unable to write settings");
+        .map_err(StorageError::io("open", &path))?;
+    file.lock().map_err(StorageError::io("lock", &path))?;
+    Ok(file)
+}
+
+fn fingerprint(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Parses the settings file, upgrading it in memory if it uses an older
+/// schema. Returns the settings and the version they were upgraded from.
+fn parse_user_settings(path: &Path, text: &str) -> Result<(UserSettings, u32), StorageError> {
+    let mut document: Value =
+        serde_json::from_str(text).map_err(|error| StorageError::parse(path, error))?;
+    let from_version = document_version(&document);
+    let mut user_settings: UserSettings = if from_version == CURRENT_VERSION {
+        // Deserializing from the text keeps line and column numbers in errors.
+        serde_json::from_str(text).map_err(|error| StorageError::parse(path, error))?
+    } else {
+        migrate(&mut document).map_err(|message| StorageError::Migration {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        serde_json::from_value(document).map_err(|error| StorageError::parse(path, error))?
+    };
+    user_settings.file_fingerprint = Some(fingerprint(text.as_bytes()));
+    Ok((user_settings, from_version))
+}
+
+pub fn load_user_settings() -> Result<UserSettings, StorageError> {
+    ensure_config_dir()?;
+    let _lock = lock_settings()?;
+    let path = get_config_path()?;
+    if !path.exists() {
+        let mut default_settings = UserSettings::new(Timezone::default().now().date());
+        save_user_settings(&mut default_settings)?;
+        return Ok(default_settings);
+    }
+
+    let text = read_settings_text(&path)?;
+    let (mut user_settings, from_version) = parse_user_settings(&path, &text)?;
+    if from_version < CURRENT_VERSION {
+        let backup_path = backup_settings_file(Some(&format!("v{}", from_version)))?;
+        save_user_settings(&mut user_settings)?;
+        println!(
+            "Upgraded the settings file from version {} to {}. The previous file was saved to {}.",
+            from_version,
+            CURRENT_VERSION,
+            backup_path.display()
+        );
+    }
+    Ok(user_settings)
+}
+
+/// Applies `change` to the settings and saves them while holding the lock.
+///
+/// If another process saved since these settings were loaded, they are first
+/// reloaded from disk and the change is applied to that copy instead, so a
+/// stale copy never overwrites entries it has not seen.
+pub fn update_user_settings<T>(
+    user_settings: &mut UserSettings,
+    change: impl FnOnce(&mut UserSettings) -> T,
+) -> Result<T, StorageError> {
+    ensure_config_dir()?;
+    let _lock = lock_settings()?;
+    let path = get_config_path()?;
+    if path.exists() {
+        let text = read_settings_text(&path)?;
+        if user_settings.file_fingerprint != Some(fingerprint(text.as_bytes())) {
+            let (fresh, _) = parse_user_settings(&path, &text)?;
+            *user_settings = fresh;
+            println!("(Picked up changes saved by another crawl-todo session.)");
+        }
+    }
+    let result = change(user_settings);
+    save_user_settings(user_settings)?;
+    Ok(result)
+}
+
+/// Runs the migration chain on a copy of the settings file without saving it.
+/// Returns the version found on disk and the changes migrating would make.
+pub fn preview_migration() -> Result<(u32, Vec<String>), StorageError> {
+    let path = get_config_path()?;
+    if !path.exists() {
+        return Ok((CURRENT_VERSION, Vec::new()));
+    }
+    let text = read_settings_text(&path)?;
+    let mut document: Value =
+        serde_json::from_str(&text).map_err(|error| StorageError::parse(&path, error))?;
+    let from_version = document_version(&document);
+    let changes =
+        migrate(&mut document).map_err(|message| StorageError::Migration { path, message })?;
+    Ok((from_version, changes))
+}
+
+/// Every backup, newest first.
+pub fn list_backups() -> Result<Vec<PathBuf>, StorageError> {
+    let backup_dir = get_backup_dir()?;
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&backup_dir).map_err(StorageError::io("list", &backup_dir))?;
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    // Backup names start with a timestamp, so sorting by name sorts by age.
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+/// The most recent backup that is worth restoring. Files set aside by a
+/// restore are skipped, since they are often the broken file itself.
+pub fn latest_backup() -> Result<Option<PathBuf>, StorageError> {
+    Ok(list_backups()?.into_iter().find(|path| {
+        !path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.ends_with("-replaced"))
+    }))
+}
+
+/// Replaces the settings file with a backup, keeping the replaced file as a
+/// backup of its own. The lock is held throughout so that another session
+/// cannot write its copy back over the restored file.
+pub fn restore_backup(backup_path: &Path) -> Result<(), StorageError> {
+    ensure_config_dir()?;
+    let _lock = lock_settings()?;
+    let path = get_config_path()?;
+    let contents = fs::read(backup_path).map_err(StorageError::io("read", backup_path))?;
+    if path.exists() {
+        backup_settings_file(Some("replaced"))?;
+    }
+    write_atomically(&path, &contents)
+}
+
+fn save_user_settings(user_settings: &mut UserSettings) -> Result<(), StorageError> {
+    ensure_config_dir()?;
+    let path = get_config_path()?;
+    let contents = serde_json::to_vec_pretty(user_settings).map_err(|error| StorageError::Io {
+        action: "serialize",
+        path: path.clone(),
+        source: error.into(),
+    })?;
+    // Take one rolling backup per run, before the first write replaces the
+    // file, unless a labelled backup of it was just taken.
+    if user_settings.backup_count > 0 && path.exists() && !BACKED_UP.swap(true, Ordering::SeqCst) {
+        backup_settings_file(None)?;
+        prune_backups(user_settings.backup_count)?;
+    }
+    write_atomically(&path, &contents)?;
+    user_settings.file_fingerprint = Some(fingerprint(&contents));
+    Ok(())
+}
+
+/// Reads a date written either as ISO-8601 or in the legacy `%d/%m/%Y` form.
+pub fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_date(&text).ok_or_else(|| serde::de::Error::custom(format!("invalid date '{}'", text)))
+}
+
+/// Reads an optional time of day. Values that cannot be parsed are dropped so
+/// the program asks for them again instead of refusing to start.
+pub fn deserialize_optional_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveTime>, D::Error> {
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.as_deref().and_then(parse_time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::Mutex;
+
+    /// Tests that touch the settings file share `HOME`, so they take turns.
+    static HOME: Mutex<()> = Mutex::new(());
+
+    fn in_temp_home(name: &str, test: impl FnOnce()) {
+        let _guard = HOME.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let home = env::temp_dir().join(format!("crawl-todo-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        env::set_var("HOME", &home);
+        test();
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn stale_copy_is_reloaded_before_saving() {
+        in_temp_home("stale", || {
+            let mut first = load_user_settings().unwrap();
+            let mut second = load_user_settings().unwrap();
+            let add = |title: &str| {
+                let title = title.to_string();
+                move |settings: &mut UserSettings| {
+                    let now = settings.now();
+                    settings.todos.add(title, None, now)
+                }
+            };
+            assert_eq!(update_user_settings(&mut first, add("one")).unwrap(), 1);
+            // `second` was loaded before `first` saved, so it must pick up
+            // item #1 rather than overwrite it.
+            assert_eq!(update_user_settings(&mut second, add("two")).unwrap(), 2);
+
+            let saved = load_user_settings().unwrap();
+            let titles: Vec<_> = saved.todos.items().iter().map(|item| &item.title).collect();
+            assert_eq!(titles, ["one", "two"]);
+        });
+    }
+
+    #[test]
+    fn backups_in_the_same_second_are_kept_apart() {
+        in_temp_home("backups", || {
+            load_user_settings().unwrap();
+            let first = backup_settings_file(Some("replaced")).unwrap();
+            let second = backup_settings_file(Some("replaced")).unwrap();
+            let rolling = backup_settings_file(None).unwrap();
+            let another = backup_settings_file(None).unwrap();
+            assert_ne!(first, second);
+            assert_ne!(rolling, another);
+            assert_eq!(list_backups().unwrap().len(), 4);
+            // Later backups still sort as newer, and keep their kind.
+            assert!(is_rolling_backup(&rolling) && is_rolling_backup(&another));
+            assert!(!is_rolling_backup(&second));
+            assert_eq!(latest_backup().unwrap(), Some(another));
+        });
+    }
 }
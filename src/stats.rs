@@ -1,120 +1,727 @@
-use crate::task::UserSettings;
-use chrono::{Datelike, NaiveDate};
-use std::collections::HashMap;
+use crate::category::CategoryClass;
+use crate::chart::Canvas;
+use crate::clock::format_duration;
+use crate::goal::{Goal, GoalOutcome, GoalPeriod};
+use crate::task::{DayStatus, TodaysTasks, UserSettings};
+use chrono::{Datelike, Days, Duration, NaiveDate};
+use colored::*;
+use std::collections::{BTreeMap, HashMap};
 
-pub fn display_stats(user_settings: &UserSettings, period: &str) {
-    match period {
-        "weekly" => display_weekly_stats(user_settings),
-        "monthly" => display_monthly_stats(user_settings),
-        _ => println!("Invalid period. Use 'daily', 'weekly', or 'monthly'."),
+/// Order of the rows in a report's category table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// Most time first.
+    #[default]
+    Time,
+    Name,
+    /// Grouped by class, most time first within each.
+    Class,
+}
+
+impl SortOrder {
+    pub fn parse(input: &str) -> Option<SortOrder> {
+        match input.to_lowercase().as_str() {
+            "time" => Some(SortOrder::Time),
+            "name" => Some(SortOrder::Name),
+            "class" => Some(SortOrder::Class),
+            _ => None,
+        }
     }
 }
 
-fn display_weekly_stats(user_settings: &UserSettings) {
-    let current_date = NaiveDate::parse_from_str(&user_settings.today.date, "%d/%m/%Y")
-        .expect("Invalid date format in user settings");
-    let start_of_week = current_date
-        .checked_sub_signed(chrono::Duration::days(
-            current_date.weekday().num_days_from_monday() as i64,
-        ))
-        .expect("Invalid start of week");
+/// How a report is laid out.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub sort: SortOrder,
+    /// Draw bar charts and a sparkline after the tables.
+    pub chart: bool,
+    /// How many days the sparkline of productive time covers.
+    pub sparkline_days: usize,
+}
 
-    let mut total_minutes_spent = 0;
-    let mut task_summary: HashMap<String, u64> = HashMap::new();
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions {
+            sort: SortOrder::default(),
+            chart: false,
+            sparkline_days: 30,
+        }
+    }
+}
 
-    for day in &user_settings.past_tasks {
-        let date = NaiveDate::parse_from_str(&day.date, "%d/%m/%Y")
-            .expect("Invalid date format in past tasks");
-        if date >= start_of_week && date <= current_date {
-            total_minutes_spent += day.total_minutes_spent();
-            update_task_summary(&mut task_summary, day);
+/// Prints the report for a named period: `daily`, `weekly` (Monday to
+/// today), `monthly` (the calendar month so far), `last-week`, `last-month`,
+/// `yearly` (the calendar year so far), `all`, `ytd`, or `last-30d` for any number of days ending today.
+pub fn display_stats(
+    user_settings: &UserSettings,
+    period: &str,
+    options: &ReportOptions,
+) -> Result<(), String> {
+    let today = user_settings.today.date;
+    let start_of_month = today.with_day(1).unwrap();
+    match period {
+        "daily" => display_daily_stats(user_settings, options),
+        "weekly" => display_range_stats(
+            user_settings,
+            options,
+            start_of_week(today),
+            today,
+            "in the past week",
+            "Weekly Task Summary",
+        ),
+        "monthly" => display_range_stats(
+            user_settings,
+            options,
+            start_of_month,
+            today,
+            "in the past month",
+            "Monthly Task Summary",
+        ),
+        "last-week" => {
+            let monday = start_of_week(today) - Duration::days(7);
+            display_range_stats(
+                user_settings,
+                options,
+                monday,
+                monday + Duration::days(6),
+                "last week",
+                "Task Summary for Last Week",
+            )
+        }
+        "last-month" => {
+            let end = start_of_month.pred_opt().unwrap();
+            display_range_stats(
+                user_settings,
+                options,
+                end.with_day(1).unwrap(),
+                end,
+                "last month",
+                "Task Summary for Last Month",
+            )
+        }
+        "yearly" => {
+            let start_of_year = today.with_ordinal(1).unwrap();
+            display_range_stats(
+                user_settings,
+                options,
+                start_of_year,
+                today,
+                "this year",
+                "Yearly Task Summary",
+            );
+            display_month_subtotals(user_settings, start_of_year, today);
+        }
+        "all" => {
+            let first_day = user_settings.days().map(|day| day.date).min().unwrap();
+            display_range_stats(
+                user_settings,
+                options,
+                first_day,
+                today,
+                "over all recorded days",
+                "All-Time Task Summary",
+            );
+            display_month_subtotals(user_settings, first_day, today);
+        }
+        "ytd" => display_range_stats(
+            user_settings,
+            options,
+            today.with_ordinal(1).unwrap(),
+            today,
+            "this year",
+            "Year to Date Task Summary",
+        ),
+        _ => {
+            let (days, from) = last_days(period, today).ok_or_else(|| {
+                    format!(
+                        "Unknown period '{}'. Use daily, weekly, monthly, yearly, all, last-week, last-month, ytd or last-30d.",
+                        period
+                    )
+                })?;
+            display_range_stats(
+                user_settings,
+                options,
+                from,
+                today,
+                &format!("in the last {} days", days),
+                &format!("Task Summary for the Last {} Days", days),
+            )
         }
     }
+    Ok(())
+}
 
-    // Include today's tasks in the summary
-    total_minutes_spent += user_settings.today.total_minutes_spent();
+/// Reads a `last-30d` period: the number of days, and the first of them when
+/// the last is `today`.
+fn last_days(period: &str, today: NaiveDate) -> Option<(u64, NaiveDate)> {
+    let days = period
+        .strip_prefix("last-")?
+        .strip_suffix('d')?
+        .parse::<u64>()
+        .ok()
+        .filter(|days| *days > 0)?;
+    Some((days, today.checked_sub_days(Days::new(days - 1))?))
+}
+
+/// Prints the report for the days from `from` to `to`, both included.
+pub fn display_date_range_stats(
+    user_settings: &UserSettings,
+    from: NaiveDate,
+    to: NaiveDate,
+    options: &ReportOptions,
+) {
+    let range = format!("{} to {}", from.format("%d/%m/%Y"), to.format("%d/%m/%Y"));
+    display_range_stats(
+        user_settings,
+        options,
+        from,
+        to,
+        &format!("from {}", range),
+        &format!("Task Summary, {}", range),
+    );
+}
+
+fn display_daily_stats(user_settings: &UserSettings, options: &ReportOptions) {
+    let mut task_summary = Summary::new();
     update_task_summary(&mut task_summary, &user_settings.today);
+    let total_minutes_spent = user_settings.today.total_minutes_spent();
 
     println!(
-        "Total time spent in the past week: {} hours and {} minutes",
+        "\nTotal time spent today: {} hours and {} minutes",
         total_minutes_spent / 60,
         total_minutes_spent % 60
     );
 
-    display_task_summary("Weekly Task Summary", &task_summary);
+    display_schedule_progress(user_settings, &[&user_settings.today]);
+    display_current_goals(user_settings);
+
+    let (scheduled_minutes, _) = schedule_totals(user_settings, &[&user_settings.today]);
+    display_task_summary(
+        "Daily Task Summary",
+        &task_summary,
+        scheduled_minutes,
+        options.sort,
+    );
+    if options.chart {
+        let today = user_settings.today.date;
+        display_charts(user_settings, options, &task_summary, today, today);
+    }
 }
 
-fn display_monthly_stats(user_settings: &UserSettings) {
-    let current_date = NaiveDate::parse_from_str(&user_settings.today.date, "%d/%m/%Y")
-        .expect("Invalid date format in user settings");
+fn display_range_stats(
+    user_settings: &UserSettings,
+    options: &ReportOptions,
+    from: NaiveDate,
+    to: NaiveDate,
+    label: &str,
+    title: &str,
+) {
+    // Days are stored under the date they started on, counting from
+    // `day_starts_at`, so work after midnight stays with its evening.
+    let days: Vec<&TodaysTasks> = user_settings
+        .days()
+        .filter(|day| day.date >= from && day.date <= to)
+        .collect();
 
     let mut total_minutes_spent = 0;
-    let mut task_summary: HashMap<String, u64> = HashMap::new();
+    let mut task_summary = Summary::new();
+    for day in &days {
+        total_minutes_spent += day.total_minutes_spent();
+        update_task_summary(&mut task_summary, day);
+    }
+
+    println!(
+        "Total time spent {}: {} hours and {} minutes",
+        label,
+        total_minutes_spent / 60,
+        total_minutes_spent % 60
+    );
+    display_day_counts(&days);
+    display_schedule_progress(user_settings, &days);
+    display_goal_history(user_settings, from, to);
+
+    let (scheduled_minutes, _) = schedule_totals(user_settings, &days);
+    display_task_summary(title, &task_summary, scheduled_minutes, options.sort);
+    if options.chart {
+        display_charts(user_settings, options, &task_summary, from, to);
+    }
+}
 
-    for day in &user_settings.past_tasks {
-        let date = NaiveDate::parse_from_str(&day.date, "%d/%m/%Y")
-            .expect("Invalid date format in past tasks");
-        if date.month() == current_date.month() && date.year() == current_date.year() {
-            total_minutes_spent += day.total_minutes_spent();
-            update_task_summary(&mut task_summary, day);
+/// Lists the time and tracked days of each month in the range, then names
+/// the best and worst of the months that are over.
+fn display_month_subtotals(user_settings: &UserSettings, from: NaiveDate, to: NaiveDate) {
+    let mut months: BTreeMap<(i32, u32), (u64, usize)> = BTreeMap::new();
+    for day in user_settings
+        .days()
+        .filter(|day| day.date >= from && day.date <= to)
+    {
+        let (minutes, tracked) = months
+            .entry((day.date.year(), day.date.month()))
+            .or_insert((0, 0));
+        *minutes += day.total_minutes_spent();
+        if day.status.is_tracked() {
+            *tracked += 1;
         }
     }
 
-    // Include today's tasks in the summary if it's in the current month
-    if current_date.month()
-        == NaiveDate::parse_from_str(&user_settings.today.date, "%d/%m/%Y")
+    println!("\n{}", "Monthly Subtotals".bold());
+    let month_name = |(year, month): (i32, u32)| {
+        NaiveDate::from_ymd_opt(year, month, 1)
             .unwrap()
-            .month()
-    {
-        total_minutes_spent += user_settings.today.total_minutes_spent();
-        update_task_summary(&mut task_summary, &user_settings.today);
+            .format("%B %Y")
+            .to_string()
+    };
+    for (month, (minutes, tracked)) in &months {
+        println!(
+            "{:<15} {:>4} hours and {:>2} minutes over {} tracked days",
+            month_name(*month),
+            minutes / 60,
+            minutes % 60,
+            tracked
+        );
+    }
+
+    let current_month = (to.year(), to.month());
+    let finished: Vec<_> = months
+        .iter()
+        .filter(|(month, (_, tracked))| **month < current_month && *tracked > 0)
+        .collect();
+    if finished.len() < 2 {
+        return;
+    }
+    // The earliest month wins a tie, hence the reversed search for the best.
+    let best = finished
+        .iter()
+        .rev()
+        .max_by_key(|(_, (minutes, _))| *minutes);
+    let worst = finished.iter().min_by_key(|(_, (minutes, _))| *minutes);
+    for (label, month) in [("Best", best), ("Worst", worst)] {
+        if let Some((month, (minutes, _))) = month {
+            println!(
+                "{} month: {} with {} hours and {} minutes",
+                label,
+                month_name(**month),
+                minutes / 60,
+                minutes % 60
+            );
+        }
+    }
+}
+
+/// Says how many of the days in a period were tracked, skipped, off or
+/// holidays, and averages the time over the tracked days only.
+fn display_day_counts(days: &[&TodaysTasks]) {
+    let count = |status: DayStatus| days.iter().filter(|day| day.status == status).count();
+    let tracked = count(DayStatus::Tracked);
+    let mut counts = vec![format!("{} tracked", tracked)];
+    for (status, one, many) in [
+        (DayStatus::Untracked, "untracked", "untracked"),
+        (DayStatus::DayOff, "day off", "days off"),
+        (DayStatus::Holiday, "holiday", "holidays"),
+    ] {
+        match count(status) {
+            0 => {}
+            1 => counts.push(format!("1 {}", one)),
+            days => counts.push(format!("{} {}", days, many)),
+        }
     }
+    println!("Days: {}", counts.join(", "));
 
+    if tracked > 0 {
+        let tracked_minutes: u64 = days
+            .iter()
+            .filter(|day| day.status.is_tracked())
+            .map(|day| day.total_minutes_spent())
+            .sum();
+        let average = tracked_minutes / tracked as u64;
+        println!(
+            "Average per tracked day: {} hours and {} minutes",
+            average / 60,
+            average % 60
+        );
+    }
+}
+
+/// Working time scheduled for the days, and the productive time spent on
+/// them. Untracked days and days with no known schedule are left out.
+fn schedule_totals(user_settings: &UserSettings, days: &[&TodaysTasks]) -> (u64, u64) {
+    let mut scheduled_minutes = 0;
+    let mut productive_minutes = 0;
+    for day in days {
+        if day.status == DayStatus::Untracked {
+            continue;
+        }
+        if let Some(scheduled) = user_settings.scheduled_minutes(day) {
+            scheduled_minutes += scheduled;
+            productive_minutes += day.productive_minutes_spent();
+        }
+    }
+    (scheduled_minutes, productive_minutes)
+}
+
+/// Compares productive time with the working time scheduled for the same
+/// days.
+fn display_schedule_progress(user_settings: &UserSettings, days: &[&TodaysTasks]) {
+    let (scheduled_minutes, productive_minutes) = schedule_totals(user_settings, days);
+    if scheduled_minutes == 0 {
+        return;
+    }
     println!(
-        "Total time spent in the past month: {} hours and {} minutes",
-        total_minutes_spent / 60,
-        total_minutes_spent % 60
+        "Productive for {} hours and {} minutes of {} hours and {} minutes scheduled ({}%)",
+        productive_minutes / 60,
+        productive_minutes % 60,
+        scheduled_minutes / 60,
+        scheduled_minutes % 60,
+        productive_minutes * 100 / scheduled_minutes
     );
+}
 
-    display_task_summary("Monthly Task Summary", &task_summary);
+fn start_of_week(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
 }
 
-fn update_task_summary(summary: &mut HashMap<String, u64>, day: &crate::task::TodaysTasks) {
-    for (task_name, task) in &day.todays_tasks {
-        *summary.entry(task_name.clone()).or_insert(0) += task.minutes_spent;
+/// Splits `from..=to` into the goal's days or weeks and judges each one.
+/// Weeks are counted in full, even where they reach outside the range.
+fn goal_periods(
+    user_settings: &UserSettings,
+    goal: &Goal,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(u64, GoalOutcome)> {
+    let (mut start, length) = match goal.period {
+        GoalPeriod::Day => (from, 1),
+        GoalPeriod::Week => (start_of_week(from), 7),
+    };
+    let today = user_settings.today.date;
+    let mut periods = Vec::new();
+    while start <= to {
+        let end = start + Duration::days(length - 1);
+        let days: Vec<&TodaysTasks> = user_settings
+            .days()
+            .filter(|day| day.date >= start && day.date <= end && day.status.is_tracked())
+            .collect();
+        let minutes = days.iter().map(|day| day.minutes_for(&goal.category)).sum();
+        let outcome = if days.is_empty() {
+            GoalOutcome::Skipped
+        } else {
+            goal.outcome(minutes, end < today)
+        };
+        periods.push((minutes, outcome));
+        start = end + Duration::days(1);
     }
-    for (task_name, task) in &day.todays_chores {
-        *summary.entry(task_name.clone()).or_insert(0) += task.minutes_spent;
+    periods
+}
+
+/// Shows how each goal stands for the day or week that is under way.
+pub fn display_current_goals(user_settings: &UserSettings) {
+    let goals = user_settings.goals.all();
+    if goals.is_empty() {
+        return;
     }
-    for (task_name, task) in &day.todays_entertainment {
-        *summary.entry(task_name.clone()).or_insert(0) += task.minutes_spent;
+    let today = user_settings.today.date;
+    println!("\n{}", "Goals".bold());
+    for goal in goals {
+        let (minutes, outcome) = goal_periods(user_settings, goal, today, today)[0];
+        let verdict = match outcome {
+            GoalOutcome::Met => " - met".green(),
+            GoalOutcome::Missed => " - missed".red(),
+            GoalOutcome::Pending => "".normal(),
+            GoalOutcome::Skipped => " - not counted, nothing tracked".normal(),
+        };
+        println!(
+            "{}: {} ({}%){}",
+            goal,
+            format_duration(minutes),
+            goal.progress(minutes),
+            verdict
+        );
     }
 }
 
-fn display_task_summary(title: &str, tasks: &HashMap<String, u64>) {
-    // ANSI escape codes for text formatting
-    const BLUE_BOLD: &str = "\x1b[1;34m";
-    const BOLD: &str = "\x1b[1m";
-    const RESET: &str = "\x1b[0m";
+/// How many days or weeks a goal's history shows at most.
+const GOAL_MARKS: usize = 31;
 
-    println!("\n{}{}{}", BLUE_BOLD, title, RESET);
-    for (task_name, minutes_spent) in tasks {
+/// Shows each goal's progress over a report's range, with one mark per day
+/// or week: met, missed, still open, or not tracked.
+fn display_goal_history(user_settings: &UserSettings, from: NaiveDate, to: NaiveDate) {
+    let goals = user_settings.goals.all();
+    if goals.is_empty() {
+        return;
+    }
+    println!("\n{}", "Goals".bold());
+    for goal in goals {
+        let periods = goal_periods(user_settings, goal, from, to);
+        let counted: Vec<_> = periods
+            .iter()
+            .filter(|(_, outcome)| *outcome != GoalOutcome::Skipped)
+            .collect();
+        let count = |wanted: GoalOutcome| {
+            counted
+                .iter()
+                .filter(|(_, outcome)| *outcome == wanted)
+                .count()
+        };
+        let (met, missed) = (count(GoalOutcome::Met), count(GoalOutcome::Missed));
+        let minutes: u64 = counted.iter().map(|(minutes, _)| minutes).sum();
+        let progress = goal.progress(minutes) / (counted.len() as u64).max(1);
+        // Long ranges only show their most recent periods.
+        let shown = periods.len().min(GOAL_MARKS);
+        let mut marks: Vec<String> = periods[periods.len() - shown..]
+            .iter()
+            .map(|(_, outcome)| match outcome {
+                GoalOutcome::Met => "✓".green().to_string(),
+                GoalOutcome::Missed => "✗".red().to_string(),
+                GoalOutcome::Pending => "?".to_string(),
+                GoalOutcome::Skipped => "·".to_string(),
+            })
+            .collect();
+        if shown < periods.len() {
+            marks.insert(0, "…".to_string());
+        }
         println!(
-            "{}{}{}: {} hours and {} minutes",
-            BOLD,
-            task_name,
-            RESET,
-            minutes_spent / 60,
-            minutes_spent % 60
+            "{}: {}%, met {} of {} {}s  {}",
+            goal,
+            progress,
+            met,
+            met + missed,
+            goal.period,
+            marks.join("")
         );
     }
 }
 
-trait TaskSummary {
+/// Minutes per category, kept apart by the class each was recorded under.
+type Summary = HashMap<(CategoryClass, String), u64>;
+
+fn update_task_summary(summary: &mut Summary, day: &TodaysTasks) {
+    for class in CategoryClass::ALL {
+        for (task_name, task) in day.bucket(class) {
+            *summary.entry((class, task_name.clone())).or_insert(0) += task.minutes_spent;
+        }
+    }
+}
+
+/// Prints the time per category as a table, followed by a subtotal per
+/// class with its share of the tracked time and, when the days had scheduled
+/// hours, how it compares with them. Ties are broken by name so that the
+/// same data always gives the same table.
+fn display_task_summary(title: &str, tasks: &Summary, scheduled_minutes: u64, sort: SortOrder) {
+    println!("\n{}", title.blue().bold());
+    let total: u64 = tasks.values().sum();
+    let share = |minutes: u64| format!("{}%", (minutes * 200 + total) / (total * 2).max(1));
+
+    let rows = sorted_rows(tasks, sort);
+
+    let width = rows
+        .iter()
+        .map(|(_, name, _)| name.chars().count())
+        .chain(["Category".len(), "Total".len()])
+        .max()
+        .unwrap_or(0);
+    let header = format!(
+        "{:<width$}  {:<13}  {:>9}  {:>4}",
+        "Category",
+        "Class",
+        "Time",
+        "%",
+        width = width
+    );
+    println!("{}", header.bold());
+    for (class, name, minutes) in &rows {
+        println!(
+            "{:<width$}  {:<13}  {:>9}  {:>4}",
+            name,
+            class.to_string(),
+            table_time(*minutes),
+            share(*minutes),
+            width = width
+        );
+    }
+    let totals = format!(
+        "{:<width$}  {:<13}  {:>9}  {:>4}",
+        "Total",
+        "",
+        table_time(total),
+        share(total),
+        width = width
+    );
+    println!("{}", totals.bold());
+
+    println!();
+    let scheduled_header = if scheduled_minutes > 0 {
+        "  Of scheduled"
+    } else {
+        ""
+    };
+    let header = format!(
+        "{:<13}  {:>9}  {:>4}{}",
+        "Class", "Time", "%", scheduled_header
+    );
+    println!("{}", header.bold());
+    for class in CategoryClass::ALL {
+        let subtotal: u64 = rows
+            .iter()
+            .filter(|(task_class, _, _)| **task_class == class)
+            .map(|(_, _, minutes)| minutes)
+            .sum();
+        if subtotal == 0 {
+            continue;
+        }
+        let ratio = if scheduled_minutes > 0 {
+            format!("  {:>12.2}", subtotal as f64 / scheduled_minutes as f64)
+        } else {
+            String::new()
+        };
+        println!(
+            "{:<13}  {:>9}  {:>4}{}",
+            class.to_string(),
+            table_time(subtotal),
+            share(subtotal),
+            ratio
+        );
+    }
+}
+
+/// The summary's categories in the requested order, with their class and
+/// minutes.
+fn sorted_rows(tasks: &Summary, sort: SortOrder) -> Vec<(&CategoryClass, &String, u64)> {
+    let mut rows: Vec<(&CategoryClass, &String, u64)> = tasks
+        .iter()
+        .map(|((class, name), minutes)| (class, name, *minutes))
+        .collect();
+    rows.sort_by(|a, b| {
+        let by_time = b.2.cmp(&a.2);
+        let by_name = a.1.to_lowercase().cmp(&b.1.to_lowercase());
+        match sort {
+            SortOrder::Time => by_time.then(by_name),
+            SortOrder::Name => by_name.then(by_time),
+            SortOrder::Class => a.0.cmp(b.0).then(by_time).then(by_name),
+        }
+    });
+    rows
+}
+
+/// A day's categories and their minutes, in the order `sort` gives the
+/// report tables.
+pub fn day_breakdown(day: &TodaysTasks, sort: SortOrder) -> Vec<(String, u64)> {
+    let mut summary = Summary::new();
+    update_task_summary(&mut summary, day);
+    sorted_rows(&summary, sort)
+        .into_iter()
+        .map(|(_, name, minutes)| (name.clone(), minutes))
+        .collect()
+}
+
+/// Draws the charts for a report: a bar per category, a column per day for
+/// ranges of up to a month, and a sparkline of productive time per day.
+fn display_charts(
+    user_settings: &UserSettings,
+    options: &ReportOptions,
+    tasks: &Summary,
+    from: NaiveDate,
+    to: NaiveDate,
+) {
+    let canvas = Canvas::detect();
+    let rows = sorted_rows(tasks, options.sort);
+    if let Some(max) = rows.iter().map(|(_, _, minutes)| *minutes).max() {
+        let width = rows
+            .iter()
+            .map(|(_, name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let length = canvas.width.saturating_sub(width + 12).max(10);
+        println!("\n{}", "Time per Category".bold());
+        for (_, name, minutes) in &rows {
+            let bar = canvas.bar(*minutes, max, length);
+            let bar = match (canvas.fancy, user_settings.categories.by_name(name)) {
+                (true, Some(category)) => match category.color.parse::<Color>() {
+                    Ok(color) => bar.color(color).to_string(),
+                    Err(_) => bar,
+                },
+                _ => bar,
+            };
+            println!(
+                "{:<width$}  {:>7}  {}",
+                name,
+                format_duration(*minutes),
+                bar,
+                width = width
+            );
+        }
+    }
+
+    let day_count = (to - from).num_days() + 1;
+    if (2..=31).contains(&day_count) {
+        let columns: Vec<(String, u64)> = from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| {
+                let label = if day_count <= 7 {
+                    date.format("%a").to_string()[..2].to_string()
+                } else {
+                    date.format("%d").to_string()
+                };
+                let minutes = user_settings
+                    .day(date)
+                    .map(|day| day.total_minutes_spent())
+                    .unwrap_or(0);
+                (label, minutes)
+            })
+            .collect();
+        let max = columns
+            .iter()
+            .map(|(_, minutes)| *minutes)
+            .max()
+            .unwrap_or(0);
+        println!(
+            "\n{} (highest {})",
+            "Time per Day".bold(),
+            format_duration(max)
+        );
+        for line in canvas.columns(&columns, 8) {
+            println!("{}", line);
+        }
+    }
+
+    // One character per day, so no more days than fit on a line.
+    let days = options.sparkline_days.clamp(1, canvas.width);
+    let productive: Vec<u64> = (0..days)
+        .rev()
+        .map(|back| {
+            user_settings
+                .day(to - Duration::days(back as i64))
+                .map(|day| day.productive_minutes_spent())
+                .unwrap_or(0)
+        })
+        .collect();
+    println!(
+        "\n{} (last {} days to {}, highest {})",
+        "Productive Time".bold(),
+        days,
+        to.format("%d/%m/%Y"),
+        format_duration(productive.iter().copied().max().unwrap_or(0))
+    );
+    println!("{}", canvas.sparkline(&productive));
+}
+
+/// A number of minutes as a table cell, e.g. `17h 05m`.
+fn table_time(minutes: u64) -> String {
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+pub trait TaskSummary {
     fn total_minutes_spent(&self) -> u64;
+    fn productive_minutes_spent(&self) -> u64;
 }
 
-impl TaskSummary for crate::task::TodaysTasks {
+impl TaskSummary for TodaysTasks {
+    fn productive_minutes_spent(&self) -> u64 {
+        self.todays_tasks
+            .values()
+            .map(|task| task.minutes_spent)
+            .sum()
+    }
+
     fn total_minutes_spent(&self) -> u64 {
         self.todays_tasks
             .values()
@@ -130,5 +737,69 @@ impl TaskSummary for crate::task::TodaysTasks {
                 .values()
                 .map(|task| task.minutes_spent)
                 .sum::<u64>()
+            + self
+                .todays_custom
+                .values()
+                .map(|task| task.minutes_spent)
+                .sum::<u64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_last_days_periods() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        assert_eq!(last_days("last-1d", today), Some((1, today)));
+        assert_eq!(
+            last_days("last-30d", today),
+            NaiveDate::from_ymd_opt(2026, 9, 18).map(|from| (30, from))
+        );
+        for period in [
+            "last-0d",
+            "last-d",
+            "last-7",
+            "last--7d",
+            "last-7days",
+            "past-7d",
+        ] {
+            assert_eq!(last_days(period, today), None, "{}", period);
+        }
+        // Too many days to count back from today is refused, not a panic.
+        assert_eq!(last_days("last-18446744073709551615d", today), None);
+        assert_eq!(last_days("last-99999999999999999999d", today), None);
+    }
+
+    #[test]
+    fn sorts_rows_with_ties_broken_by_name() {
+        use CategoryClass::*;
+        let summary: Summary = [
+            ((Chore, "dishes".to_string()), 30),
+            ((Productive, "Writing".to_string()), 90),
+            ((Productive, "coding".to_string()), 30),
+            ((Entertainment, "Games".to_string()), 90),
+        ]
+        .into_iter()
+        .collect();
+        let names = |sort| -> Vec<String> {
+            sorted_rows(&summary, sort)
+                .into_iter()
+                .map(|(_, name, _)| name.clone())
+                .collect()
+        };
+        assert_eq!(
+            names(SortOrder::Time),
+            ["Games", "Writing", "coding", "dishes"]
+        );
+        assert_eq!(
+            names(SortOrder::Name),
+            ["coding", "dishes", "Games", "Writing"]
+        );
+        assert_eq!(
+            names(SortOrder::Class),
+            ["Writing", "coding", "dishes", "Games"]
+        );
     }
 }
@@ -1,108 +1,1086 @@
-use crate::serialization::save_user_settings;
+use crate::category::{Categories, CategoryClass};
+use crate::clock::{logical_date, on_day, parse_time, time_span, Timezone};
+use crate::goal::Goals;
+use crate::journal::{DatedSession, Journal, JournalEntry};
+use crate::migration::CURRENT_VERSION;
+use crate::schedule::{ScheduledDay, WorkSchedule};
+use crate::serialization::{
+    deserialize_date, deserialize_optional_time, update_user_settings, StorageError,
+};
+use crate::todo::TodoList;
 use crate::user_interaction::get_time_from_user; // Import the function
-use chrono::{NaiveTime, Utc};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap; // Import the function
+use std::fmt;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Task {
     pub minutes_spent: u64,
 }
 
+/// A single timed block of work. The per-category `minutes_spent` totals are
+/// derived from these; days saved before sessions existed only have totals.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    /// Unique across all days; assigned when the session is recorded.
+    pub id: u32,
+    pub category: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Session {
+    pub fn minutes(&self) -> u64 {
+        self.end
+            .signed_duration_since(self.start)
+            .num_minutes()
+            .max(0) as u64
+    }
+
+    pub fn overlaps(&self, other: &Session) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// Cuts the session wherever it runs into a new day, with days starting
+    /// at `day_start`. A session that ends exactly at the boundary is left
+    /// whole.
+    pub fn split_into_days(self, day_start: NaiveTime) -> Vec<Session> {
+        let mut parts = Vec::new();
+        let mut rest = self;
+        while let Some(next_day) = logical_date(rest.start, day_start).succ_opt() {
+            let boundary = next_day.and_time(day_start);
+            if rest.end <= boundary {
+                break;
+            }
+            let mut part = rest.clone();
+            part.end = boundary;
+            rest.start = boundary;
+            parts.push(part);
+        }
+        parts.push(rest);
+        parts
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "#{} {} {} - {} on {}",
+            self.id,
+            self.category,
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.start.format("%d/%m/%Y")
+        )
+    }
+}
+
+/// Which day a session that runs into the next day is recorded under. Days
+/// start at midnight unless `day_starts_at` says otherwise.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum MidnightRule {
+    /// Split it where the day changes and record each part on its own day.
+    #[default]
+    Split,
+    /// Record all of it on the day it started.
+    StartDay,
+    /// Record all of it on the day it ended.
+    EndDay,
+}
+
+impl MidnightRule {
+    pub fn parse(input: &str) -> Option<MidnightRule> {
+        match input.to_lowercase().as_str() {
+            "split" => Some(MidnightRule::Split),
+            "start-day" | "start" => Some(MidnightRule::StartDay),
+            "end-day" | "end" => Some(MidnightRule::EndDay),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MidnightRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MidnightRule::Split => "split",
+            MidnightRule::StartDay => "start-day",
+            MidnightRule::EndDay => "end-day",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A timer started with `crawl-todo start` that has not been stopped yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ActiveSession {
+    pub category: String,
+    pub start: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl ActiveSession {
+    pub fn finish(self, end: NaiveDateTime, note: Option<String>) -> Session {
+        Session {
+            id: 0,
+            category: self.category,
+            start: self.start,
+            end,
+            note: note.or(self.note),
+        }
+    }
+}
+
+/// What kind of day a recorded day was. Days skipped while the program was not
+/// run are filled in at rollover so that they are not mistaken for days
+/// without any work.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum DayStatus {
+    #[default]
+    Tracked,
+    Untracked,
+    DayOff,
+    Holiday,
+}
+
+impl DayStatus {
+    pub fn parse(input: &str) -> Option<DayStatus> {
+        match input.to_lowercase().as_str() {
+            "tracked" => Some(DayStatus::Tracked),
+            "untracked" => Some(DayStatus::Untracked),
+            "day-off" | "off" => Some(DayStatus::DayOff),
+            "holiday" => Some(DayStatus::Holiday),
+            _ => None,
+        }
+    }
+
+    pub fn is_tracked(&self) -> bool {
+        *self == DayStatus::Tracked
+    }
+}
+
+impl fmt::Display for DayStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DayStatus::Tracked => "tracked",
+            DayStatus::Untracked => "untracked",
+            DayStatus::DayOff => "day off",
+            DayStatus::Holiday => "holiday",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TodaysTasks {
-    pub date: String,
-    pub start_time: Option<String>,
+    #[serde(deserialize_with = "deserialize_date")]
+    pub date: NaiveDate,
+    #[serde(default, deserialize_with = "deserialize_optional_time")]
+    pub start_time: Option<NaiveTime>,
     pub todays_tasks: HashMap<String, Task>,
     pub todays_chores: HashMap<String, Task>,
     pub todays_entertainment: HashMap<String, Task>,
+    #[serde(default)]
+    pub todays_custom: HashMap<String, Task>,
+    #[serde(default)]
+    pub sessions: Vec<Session>,
+    #[serde(default, skip_serializing_if = "DayStatus::is_tracked")]
+    pub status: DayStatus,
+    /// Working time the schedule planned for this day when it began, so that
+    /// later schedule changes do not rewrite history.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_minutes: Option<u64>,
 }
 
 impl TodaysTasks {
-    pub fn new(date: String, start_time: Option<String>) -> Self {
+    pub fn new(date: NaiveDate, start_time: Option<NaiveTime>) -> Self {
         TodaysTasks {
             date,
             start_time,
             todays_tasks: HashMap::new(),
             todays_chores: HashMap::new(),
             todays_entertainment: HashMap::new(),
+            todays_custom: HashMap::new(),
+            sessions: Vec::new(),
+            status: DayStatus::Tracked,
+            scheduled_minutes: None,
+        }
+    }
+
+    /// Records a session and credits its duration to the matching category.
+    /// A day that was skipped counts as tracked once anything is logged on it.
+    pub fn add_session(&mut self, session: Session, class: CategoryClass) {
+        if self.status == DayStatus::Untracked {
+            self.status = DayStatus::Tracked;
+        }
+        self.bucket_mut(class)
+            .entry(session.category.clone())
+            .or_insert(Task { minutes_spent: 0 })
+            .minutes_spent += session.minutes();
+
+        let position = self
+            .sessions
+            .iter()
+            .position(|existing| existing.start > session.start)
+            .unwrap_or(self.sessions.len());
+        self.sessions.insert(position, session);
+    }
+
+    /// Removes a session and takes its duration back off its category.
+    pub fn remove_session(&mut self, id: u32, class: CategoryClass) -> Option<Session> {
+        let position = self.sessions.iter().position(|session| session.id == id)?;
+        let session = self.sessions.remove(position);
+        let bucket = self.bucket_mut(class);
+        if let Some(task) = bucket.get_mut(&session.category) {
+            task.minutes_spent = task.minutes_spent.saturating_sub(session.minutes());
+            if task.minutes_spent == 0 {
+                bucket.remove(&session.category);
+            }
         }
+        Some(session)
+    }
+
+    pub fn bucket(&self, class: CategoryClass) -> &HashMap<String, Task> {
+        match class {
+            CategoryClass::Productive => &self.todays_tasks,
+            CategoryClass::Chore => &self.todays_chores,
+            CategoryClass::Entertainment => &self.todays_entertainment,
+            CategoryClass::Custom => &self.todays_custom,
+        }
+    }
+
+    pub fn bucket_mut(&mut self, class: CategoryClass) -> &mut HashMap<String, Task> {
+        match class {
+            CategoryClass::Productive => &mut self.todays_tasks,
+            CategoryClass::Chore => &mut self.todays_chores,
+            CategoryClass::Entertainment => &mut self.todays_entertainment,
+            CategoryClass::Custom => &mut self.todays_custom,
+        }
+    }
+
+    /// Minutes spent on one category, whatever its class.
+    pub fn minutes_for(&self, category: &str) -> u64 {
+        [
+            &self.todays_tasks,
+            &self.todays_chores,
+            &self.todays_entertainment,
+            &self.todays_custom,
+        ]
+        .iter()
+        .filter_map(|bucket| bucket.get(category))
+        .map(|task| task.minutes_spent)
+        .sum()
+    }
+
+    fn rename_category(&mut self, old_name: &str, new_name: &str) {
+        for bucket in [
+            &mut self.todays_tasks,
+            &mut self.todays_chores,
+            &mut self.todays_entertainment,
+            &mut self.todays_custom,
+        ] {
+            if let Some(task) = bucket.remove(old_name) {
+                bucket
+                    .entry(new_name.to_string())
+                    .or_insert(Task { minutes_spent: 0 })
+                    .minutes_spent += task.minutes_spent;
+            }
+        }
+        for session in &mut self.sessions {
+            if session.category == old_name {
+                session.category = new_name.to_string();
+            }
+        }
+    }
+
+    /// A session on this day that overlaps `session`, other than `session`
+    /// itself.
+    pub fn overlapping_session(&self, session: &Session) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .find(|existing| existing.id != session.id && existing.overlaps(session))
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserSettings {
-    pub end_time: Option<String>,
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default, deserialize_with = "deserialize_optional_time")]
+    pub end_time: Option<NaiveTime>,
+    #[serde(default, skip_serializing_if = "WorkSchedule::is_empty")]
+    pub schedule: WorkSchedule,
+    /// Start time used for a new day instead of asking.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_time"
+    )]
+    pub default_start_time: Option<NaiveTime>,
+    #[serde(default)]
+    pub timezone: Timezone,
+    /// How many rolling backups of the settings file to keep.
+    #[serde(default = "default_backup_count")]
+    pub backup_count: usize,
+    #[serde(default)]
+    pub midnight_rule: MidnightRule,
+    /// When a new day begins, for people who work past midnight.
+    #[serde(default)]
+    pub day_starts_at: NaiveTime,
+    /// How days skipped between two runs are marked at rollover.
+    #[serde(default = "default_gap_status")]
+    pub gap_status: DayStatus,
+    #[serde(default)]
+    pub categories: Categories,
+    #[serde(default)]
+    pub todos: TodoList,
+    #[serde(default, skip_serializing_if = "Goals::is_empty")]
+    pub goals: Goals,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_session: Option<ActiveSession>,
+    /// Id the next recorded session will get.
+    #[serde(default)]
+    pub next_session_id: u32,
+    #[serde(default, skip_serializing_if = "Journal::is_empty")]
+    pub journal: Journal,
     pub today: TodaysTasks,
     pub past_tasks: Vec<TodaysTasks>,
+    /// Hash of the settings file as last read or written by this process,
+    /// used to notice saves made by other processes.
+    #[serde(skip)]
+    pub file_fingerprint: Option<u64>,
+}
+
+fn future_day(date: NaiveDate) -> String {
+    format!(
+        "{} has not started yet, so nothing can be recorded on it.",
+        date.format("%d/%m/%Y")
+    )
+}
+
+fn default_backup_count() -> usize {
+    10
+}
+
+fn default_gap_status() -> DayStatus {
+    DayStatus::Untracked
 }
 
 impl UserSettings {
-    pub fn new(date: String) -> Self {
+    pub fn new(date: NaiveDate) -> Self {
         UserSettings {
+            version: CURRENT_VERSION,
             end_time: None,
+            default_start_time: None,
+            schedule: WorkSchedule::default(),
+            timezone: Timezone::default(),
+            backup_count: default_backup_count(),
+            midnight_rule: MidnightRule::default(),
+            day_starts_at: NaiveTime::MIN,
+            gap_status: default_gap_status(),
+            categories: Categories::default(),
+            todos: TodoList::default(),
+            goals: Goals::default(),
+            active_session: None,
+            next_session_id: 1,
+            journal: Journal::default(),
             today: TodaysTasks::new(date, None),
             past_tasks: Vec::new(),
+            file_fingerprint: None,
+        }
+    }
+
+    /// The current wall-clock time in the configured timezone.
+    pub fn now(&self) -> NaiveDateTime {
+        self.timezone.now()
+    }
+
+    /// The day `at` counts towards, given when days start.
+    pub fn logical_date(&self, at: NaiveDateTime) -> NaiveDate {
+        logical_date(at, self.day_starts_at)
+    }
+
+    /// The day it is now. This is `today` except after switching to a
+    /// timezone that is behind, when `today` can still be ahead for a while.
+    pub fn current_date(&self) -> NaiveDate {
+        self.logical_date(self.now()).min(self.today.date)
+    }
+
+    /// Where `time` falls on the calendar during the day `date`.
+    pub fn on_day(&self, date: NaiveDate, time: NaiveTime) -> NaiveDateTime {
+        on_day(date, time, self.day_starts_at)
+    }
+
+    /// Places a start and end time on the most recent day on which the
+    /// session would already have ended.
+    pub fn recent_span(&self, start: NaiveTime, end: NaiveTime) -> (NaiveDateTime, NaiveDateTime) {
+        let now = self.now();
+        let date = self.logical_date(now);
+        let span = time_span(date, start, end, self.day_starts_at);
+        if span.1 <= now {
+            return span;
+        }
+        time_span(
+            date.pred_opt().unwrap_or(date),
+            start,
+            end,
+            self.day_starts_at,
+        )
+    }
+
+    /// The schedule entry for `date`'s weekday, if there is one.
+    pub fn scheduled_day(&self, date: NaiveDate) -> Option<ScheduledDay> {
+        self.schedule.get(date.weekday())
+    }
+
+    /// When work is meant to start on `date`: the weekday's scheduled start,
+    /// or the default start time for weekdays without a schedule.
+    pub fn scheduled_start(&self, date: NaiveDate) -> Option<NaiveTime> {
+        match self.scheduled_day(date) {
+            Some(ScheduledDay::Off) => None,
+            Some(ScheduledDay::Hours { start, .. }) => Some(start),
+            None => self.default_start_time,
+        }
+    }
+
+    /// When work is meant to end on `date`: the weekday's scheduled end, or
+    /// the end time setting for weekdays without a schedule.
+    pub fn scheduled_end(&self, date: NaiveDate) -> Option<NaiveTime> {
+        match self.scheduled_day(date) {
+            Some(ScheduledDay::Off) => None,
+            Some(ScheduledDay::Hours { end, .. }) => Some(end),
+            None => self.end_time,
+        }
+    }
+
+    /// Working time the current schedule plans for `date`, if it is known.
+    fn planned_minutes(&self, date: NaiveDate) -> Option<u64> {
+        match self.scheduled_day(date) {
+            Some(day) => Some(day.minutes()),
+            None => Some(
+                ScheduledDay::Hours {
+                    start: self.default_start_time?,
+                    end: self.end_time?,
+                }
+                .minutes(),
+            ),
+        }
+    }
+
+    /// Working time scheduled for a recorded day: nothing on days off and
+    /// holidays, otherwise what was planned when the day began.
+    pub fn scheduled_minutes(&self, day: &TodaysTasks) -> Option<u64> {
+        match day.status {
+            DayStatus::DayOff | DayStatus::Holiday => Some(0),
+            DayStatus::Tracked | DayStatus::Untracked => day
+                .scheduled_minutes
+                .or_else(|| self.planned_minutes(day.date)),
+        }
+    }
+
+    /// Plans today again after the schedule changed.
+    pub fn reschedule_today(&mut self) {
+        self.today.scheduled_minutes = self.planned_minutes(self.today.date);
+    }
+
+    fn new_day(&self, date: NaiveDate, start_time: Option<NaiveTime>) -> TodaysTasks {
+        let mut day = TodaysTasks::new(date, start_time);
+        day.scheduled_minutes = self.planned_minutes(date);
+        day
+    }
+
+    /// Archives `today` and starts a new day once the day has moved on.
+    pub fn roll_over(&mut self, now: NaiveDateTime) {
+        // Only move forward: after switching to a zone that is behind, the
+        // stored day may be "tomorrow" for a while and must not be archived.
+        let date = self.logical_date(now);
+        if self.today.date < date {
+            // The scheduled or default start wins over the time of this run.
+            let start_time = self.scheduled_start(date).or(Some(now.time()));
+            let new_today = self.new_day(date, start_time);
+            let finished = std::mem::replace(&mut self.today, new_today);
+            let mut skipped = finished.date.succ_opt();
+            self.past_tasks.push(finished);
+            while let Some(skipped_date) = skipped.filter(|skipped_date| *skipped_date < date) {
+                let mut gap = self.new_day(skipped_date, None);
+                gap.status = self.gap_status;
+                self.past_tasks.push(gap);
+                skipped = skipped_date.succ_opt();
+            }
+            self.split_running_timer(date.and_time(self.day_starts_at));
+        }
+    }
+
+    /// When sessions are split between days, records the part of a running
+    /// timer that belongs to earlier days and restarts it at `midnight`, the
+    /// start of the new day.
+    fn split_running_timer(&mut self, midnight: NaiveDateTime) {
+        if self.midnight_rule != MidnightRule::Split {
+            return;
+        }
+        let Some(active) = &mut self.active_session else {
+            return;
+        };
+        if active.start >= midnight {
+            return;
+        }
+        let earlier = Session {
+            id: 0,
+            category: active.category.clone(),
+            start: active.start,
+            end: midnight,
+            note: active.note.clone(),
+        };
+        active.start = midnight;
+        // The part ends as the new day starts, so it is always filed under an
+        // earlier day and cannot be refused. It is not journaled: the user did
+        // not log it, and undoing it would lose the time before `midnight`.
+        if let Ok(dated) = self.dated_parts(earlier) {
+            let _ = self.file_parts(dated);
+        }
+    }
+
+    /// The day the midnight rule files a session under.
+    pub fn filing_date(&self, session: &Session) -> NaiveDate {
+        match self.midnight_rule {
+            // A session ending as a day starts belongs to the day before.
+            MidnightRule::EndDay => self
+                .logical_date(session.end - Duration::seconds(1))
+                .max(self.logical_date(session.start)),
+            MidnightRule::Split | MidnightRule::StartDay => self.logical_date(session.start),
+        }
+    }
+
+    /// The pieces the midnight rule stores a session as.
+    fn session_parts(&self, session: Session) -> Vec<Session> {
+        match self.midnight_rule {
+            MidnightRule::Split => session.split_into_days(self.day_starts_at),
+            MidnightRule::StartDay | MidnightRule::EndDay => vec![session],
         }
     }
 
-    pub fn get_start_time(&mut self) -> NaiveTime {
-        let default_start_time = "Unknown".to_string();
-        let start_time = self
-            .today
-            .start_time
-            .as_ref()
-            .unwrap_or(&default_start_time);
+    /// A logged session that `session` would overlap once recorded. Under
+    /// the end-day rule a session can be filed under the day after it starts,
+    /// so each part is checked against the days either side of it as well.
+    pub fn overlapping_session(&self, session: &Session) -> Option<&Session> {
+        self.session_parts(session.clone()).iter().find_map(|part| {
+            let first = self.logical_date(part.start) - Duration::days(1);
+            let last = self.filing_date(part) + Duration::days(1);
+            self.days()
+                .filter(|day| day.date >= first && day.date <= last)
+                .find_map(|day| day.overlapping_session(part))
+        })
+    }
 
-        if start_time == "Unknown" {
-            let user_start_time =
-                get_time_from_user("Enter your start time for today (HH:MM or 'now'):");
-            self.today.start_time = Some(user_start_time.format("%H:%M:%S").to_string());
-            save_user_settings(&self);
-            user_start_time
-        } else {
-            NaiveTime::parse_from_str(start_time, "%H:%M:%S").expect("Invalid start time format")
+    /// The parts the midnight rule cuts `session` into, each with the day it
+    /// is filed under. Refused if any part would fall on a day after today.
+    fn dated_parts(&self, session: Session) -> Result<Vec<(NaiveDate, Session)>, String> {
+        let dated: Vec<(NaiveDate, Session)> = self
+            .session_parts(session)
+            .into_iter()
+            .map(|part| (self.filing_date(&part), part))
+            .collect();
+        if let Some((date, _)) = dated.iter().find(|(date, _)| *date > self.today.date) {
+            return Err(future_day(*date));
+        }
+        Ok(dated)
+    }
+
+    /// Files parts from `dated_parts` under their days and returns them. The
+    /// first part keeps the session's id if it has one; the others are given
+    /// new ids.
+    fn file_parts(
+        &mut self,
+        dated: Vec<(NaiveDate, Session)>,
+    ) -> Result<Vec<DatedSession>, String> {
+        let mut filed = Vec::new();
+        for (index, (date, mut part)) in dated.into_iter().enumerate() {
+            if part.id == 0 || index > 0 {
+                part.id = self.next_session_id;
+                self.next_session_id += 1;
+            }
+            self.file_session(date, part.clone())?;
+            filed.push(DatedSession {
+                date,
+                session: part,
+            });
         }
+        Ok(filed)
+    }
+
+    /// Records a finished session against its category's class, under the day
+    /// or days the midnight rule picks, and returns what was stored with the
+    /// newly assigned ids. Nothing is recorded if any part would fall on a
+    /// day after today.
+    pub fn record_session(&mut self, session: Session) -> Result<Vec<Session>, String> {
+        let dated = self.dated_parts(session)?;
+        let added = self.file_parts(dated)?;
+        let summary = added
+            .iter()
+            .map(|dated| dated.session.describe())
+            .collect::<Vec<_>>()
+            .join(" and ");
+        let sessions = added.iter().map(|dated| dated.session.clone()).collect();
+        self.journal.record(JournalEntry {
+            at: self.now(),
+            summary: format!("logged {}", summary),
+            added,
+            removed: Vec::new(),
+            stopped: None,
+        });
+        Ok(sessions)
+    }
+
+    /// Stops the running timer and records it as a session. Undoing the
+    /// change restarts the timer.
+    pub fn stop_timer(
+        &mut self,
+        now: NaiveDateTime,
+        note: Option<String>,
+    ) -> Result<(Session, Vec<Session>), String> {
+        let active = self
+            .active_session
+            .clone()
+            .ok_or_else(|| "No timer is running.".to_string())?;
+        let session = active.clone().finish(now, note);
+        let recorded = self.record_session(session.clone())?;
+        self.active_session = None;
+        if let Some(entry) = self.journal.last_mut() {
+            entry.summary = format!("stopped the timer and {}", entry.summary);
+            entry.stopped = Some(active);
+        }
+        Ok((session, recorded))
+    }
+
+    /// Every recorded day, newest first.
+    pub fn days(&self) -> impl Iterator<Item = &TodaysTasks> {
+        std::iter::once(&self.today).chain(self.past_tasks.iter().rev())
+    }
+
+    pub fn day(&self, date: NaiveDate) -> Option<&TodaysTasks> {
+        self.days().find(|day| day.date == date)
+    }
+
+    fn day_mut(&mut self, date: NaiveDate) -> Option<&mut TodaysTasks> {
+        std::iter::once(&mut self.today)
+            .chain(self.past_tasks.iter_mut())
+            .find(|day| day.date == date)
+    }
+
+    /// The day recorded for `date`, added to `past_tasks` in date order if it
+    /// is not there yet. Days after today are refused.
+    fn day_or_insert(&mut self, date: NaiveDate) -> Result<&mut TodaysTasks, String> {
+        if date == self.today.date {
+            return Ok(&mut self.today);
+        }
+        if date > self.today.date {
+            return Err(future_day(date));
+        }
+        let index = match self.past_tasks.iter().position(|day| day.date == date) {
+            Some(index) => index,
+            None => {
+                let index = self.past_tasks.partition_point(|day| day.date < date);
+                let day = self.new_day(date, None);
+                self.past_tasks.insert(index, day);
+                index
+            }
+        };
+        Ok(&mut self.past_tasks[index])
+    }
+
+    /// Sets what kind of day `date` was, adding it to the history if needed.
+    pub fn mark_day(&mut self, date: NaiveDate, status: DayStatus) -> Result<(), String> {
+        self.day_or_insert(date)?.status = status;
+        Ok(())
+    }
+
+    /// Looks a session up by id, along with the day it is filed under.
+    pub fn find_session(&self, id: u32) -> Option<(NaiveDate, &Session)> {
+        self.days().find_map(|day| {
+            day.sessions
+                .iter()
+                .find(|session| session.id == id)
+                .map(|session| (day.date, session))
+        })
+    }
+
+    fn file_session(&mut self, date: NaiveDate, session: Session) -> Result<(), String> {
+        let class = self.categories.class_of(&session.category);
+        self.day_or_insert(date)?.add_session(session, class);
+        Ok(())
+    }
+
+    fn unfile_session(&mut self, id: u32) -> Option<DatedSession> {
+        let (date, category) = self
+            .find_session(id)
+            .map(|(date, session)| (date, session.category.clone()))?;
+        let class = self.categories.class_of(&category);
+        let session = self.day_mut(date)?.remove_session(id, class)?;
+        Some(DatedSession { date, session })
+    }
+
+    pub fn delete_session(&mut self, id: u32) -> Result<Session, String> {
+        let removed = self
+            .unfile_session(id)
+            .ok_or_else(|| format!("There is no entry #{}.", id))?;
+        let session = removed.session.clone();
+        self.journal.record(JournalEntry {
+            at: self.now(),
+            summary: format!("deleted {}", session.describe()),
+            added: Vec::new(),
+            removed: vec![removed],
+            stopped: None,
+        });
+        Ok(session)
+    }
+
+    /// Applies `edit` to a copy of a session and, if the result is valid,
+    /// swaps it in and files it the way the midnight rule stores a new
+    /// session: the first part keeps the id, and any further parts are given
+    /// new ids. With `move_to`, the session is first shifted so that it is
+    /// filed under that day, keeping its times. `edit` is told the day the
+    /// session starts on. Returns what was stored.
+    pub fn edit_session(
+        &mut self,
+        id: u32,
+        move_to: Option<NaiveDate>,
+        edit: impl FnOnce(&mut Session, NaiveDate) -> Result<(), String>,
+    ) -> Result<Vec<Session>, String> {
+        let (filed_date, existing) = self
+            .find_session(id)
+            .ok_or_else(|| format!("There is no entry #{}.", id))?;
+        let mut edited = existing.clone();
+        if let Some(new_date) = move_to {
+            let shift = new_date - filed_date;
+            edited.start += shift;
+            edited.end += shift;
+        }
+        let start_date = self.logical_date(edited.start);
+        edit(&mut edited, start_date)?;
+        if edited.end < edited.start {
+            return Err("End time cannot be earlier than start time.".to_string());
+        }
+        let dated = self.dated_parts(edited)?;
+
+        let removed = self.unfile_session(id).expect("session was just found");
+        let added = self.file_parts(dated)?;
+        let sessions = added.iter().map(|dated| dated.session.clone()).collect();
+        self.journal.record(JournalEntry {
+            at: self.now(),
+            summary: format!("edited {}", removed.session.describe()),
+            added,
+            removed: vec![removed],
+            stopped: None,
+        });
+        Ok(sessions)
     }
 
-    pub fn get_end_time(&mut self, current_time: &chrono::DateTime<Utc>) -> chrono::DateTime<Utc> {
+    /// Reverts the most recent change to the logged sessions and returns it.
+    pub fn undo(&mut self) -> Result<JournalEntry, String> {
+        let entry = self
+            .journal
+            .pop()
+            .ok_or_else(|| "Nothing to undo.".to_string())?;
+        let refusal = match (&entry.stopped, &self.active_session) {
+            (Some(_), Some(active)) => Some(format!(
+                "A timer for {} is running. Stop it before undoing.",
+                active.category
+            )),
+            _ => entry
+                .removed
+                .iter()
+                .find(|removed| removed.date > self.today.date)
+                .map(|removed| future_day(removed.date)),
+        };
+        if let Some(refusal) = refusal {
+            self.journal.record(entry);
+            return Err(refusal);
+        }
+        for added in &entry.added {
+            self.unfile_session(added.session.id);
+        }
+        for removed in &entry.removed {
+            self.file_session(removed.date, removed.session.clone())?;
+        }
+        if let Some(stopped) = &entry.stopped {
+            self.active_session = Some(stopped.clone());
+        }
+        Ok(entry)
+    }
+
+    /// Carries a category rename through every recorded day, the journal,
+    /// goals and to-do items.
+    pub fn rename_category_in_history(&mut self, old_name: &str, new_name: &str) {
+        self.today.rename_category(old_name, new_name);
+        for day in &mut self.past_tasks {
+            day.rename_category(old_name, new_name);
+        }
+        self.journal.rename_category(old_name, new_name);
+        self.goals.rename_category(old_name, new_name);
+        self.todos.rename_category(old_name, new_name);
+    }
+
+    /// Today's start time, taken from the schedule or the default start time
+    /// when one is set and asked for otherwise.
+    pub fn get_start_time(&mut self) -> Result<NaiveTime, StorageError> {
+        match self.today.start_time {
+            Some(start_time) => Ok(start_time),
+            None => {
+                let user_start_time = match self.scheduled_start(self.today.date) {
+                    Some(default_start_time) => default_start_time,
+                    None => get_time_from_user(
+                        "Enter your start time for today (HH:MM or 'now'):",
+                        self,
+                    ),
+                };
+                update_user_settings(self, |settings| {
+                    *settings.today.start_time.get_or_insert(user_start_time)
+                })
+            }
+        }
+    }
+
+    /// When today's work should end. Asks once if neither the schedule nor
+    /// the end time setting says; an empty answer or closed input leaves it
+    /// unset. There is no end on a scheduled day off.
+    pub fn get_end_time(&mut self) -> Result<Option<NaiveDateTime>, StorageError> {
+        let date = self.current_date();
+        if self.scheduled_day(date) == Some(ScheduledDay::Off) {
+            return Ok(None);
+        }
         loop {
-            match &self.end_time {
+            if let Some(end_time) = self.scheduled_end(date) {
+                return Ok(Some(self.on_day(date, end_time)));
+            }
+            println!(
+                "Enter the desired end time for your day (e.g., 23:00), or press Enter to skip:"
+            );
+            let mut end_time = String::new();
+            let bytes_read = std::io::stdin()
+                .read_line(&mut end_time)
+                .expect("Failed to read line");
+            if bytes_read == 0 || end_time.trim().is_empty() {
+                println!("No end time set. Set one later with `crawl-todo config set end-time`.");
+                return Ok(None);
+            }
+            match parse_time(&end_time) {
                 Some(end_time) => {
-                    match chrono::NaiveDateTime::parse_from_str(
-                        &format!("{} {}", current_time.format("%Y-%m-%d"), end_time),
-                        "%Y-%m-%d %H:%M",
-                    ) {
-                        Ok(datetime) => break datetime.and_utc(),
-                        Err(_) => {
-                            println!("Invalid end time format. Please try again.");
-                            continue;
-                        }
-                    }
-                }
-                None => {
-                    println!("Enter the desired end time for your day (e.g., 23:00:00):");
-                    let mut end_time = String::new();
-                    std::io::stdin()
-                        .read_line(&mut end_time)
-                        .expect("Failed to read line");
-                    let end_time = end_time.trim().to_string();
-                    match chrono::NaiveDateTime::parse_from_str(
-                        &format!("{} {}", current_time.format("%Y-%m-%d"), end_time),
-                        "%Y-%m-%d %H:%M",
-                    ) {
-                        Ok(datetime) => {
-                            self.end_time = Some(end_time.clone());
-                            save_user_settings(&self);
-                            break datetime.and_utc();
-                        }
-                        Err(_) => {
-                            println!("Invalid end time format. Please try again!!");
-                            continue;
-                        }
-                    }
+                    update_user_settings(self, |settings| settings.end_time = Some(end_time))?;
                 }
+                None => println!("Invalid end time format. Please try again."),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: NaiveDate, time: &str) -> NaiveDateTime {
+        date.and_time(parse_time(time).unwrap())
+    }
+
+    fn session(start: NaiveDateTime, end: NaiveDateTime) -> Session {
+        Session {
+            id: 0,
+            category: "Coding".to_string(),
+            start,
+            end,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn split_into_days_cuts_at_each_day_start() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let next = day.succ_opt().unwrap();
+        let after = next.succ_opt().unwrap();
+
+        let parts = session(at(day, "23:00"), at(next, "01:00")).split_into_days(NaiveTime::MIN);
+        let spans: Vec<_> = parts.iter().map(|part| (part.start, part.end)).collect();
+        assert_eq!(
+            spans,
+            vec![
+                (at(day, "23:00"), at(next, "00:00")),
+                (at(next, "00:00"), at(next, "01:00")),
+            ]
+        );
+
+        // With days starting at 04:00, the small hours stay with the evening.
+        let four = parse_time("04:00").unwrap();
+        assert_eq!(
+            session(at(day, "23:00"), at(next, "03:00"))
+                .split_into_days(four)
+                .len(),
+            1
+        );
+        // Ending exactly as the next day starts keeps the session whole.
+        assert_eq!(
+            session(at(day, "22:00"), at(next, "04:00"))
+                .split_into_days(four)
+                .len(),
+            1
+        );
+        // Sessions spanning several boundaries are cut at each one.
+        let parts = session(at(day, "20:00"), at(after, "06:00")).split_into_days(four);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1].start, at(next, "04:00"));
+        assert_eq!(parts[1].end, at(after, "04:00"));
+        assert_eq!(parts.iter().map(Session::minutes).sum::<u64>(), 34 * 60);
+    }
+
+    #[test]
+    fn roll_over_splits_a_running_timer() {
+        let first = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        let next = first.succ_opt().unwrap();
+        let mut settings = UserSettings::new(first);
+        settings.active_session = Some(ActiveSession {
+            category: "Coding".to_string(),
+            start: at(first, "22:00"),
+            note: None,
+        });
+        settings.roll_over(at(next, "01:00"));
+
+        assert_eq!(settings.past_tasks[0].sessions.len(), 1);
+        assert_eq!(settings.past_tasks[0].minutes_for("Coding"), 120);
+        let active = settings.active_session.as_ref().unwrap();
+        assert_eq!(active.start, at(next, "00:00"));
+
+        // The split is not something the user can undo.
+        assert!(settings.journal.is_empty());
+        assert!(settings.undo().is_err());
+        assert_eq!(settings.past_tasks[0].minutes_for("Coding"), 120);
+    }
+
+    #[test]
+    fn roll_over_fills_skipped_days() {
+        let first = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        let mut settings = UserSettings::new(first);
+        settings.gap_status = DayStatus::DayOff;
+        settings.default_start_time = parse_time("09:00");
+        let now = at(first + Duration::days(3), "07:30");
+        settings.roll_over(now);
+
+        assert_eq!(settings.today.date, first + Duration::days(3));
+        assert_eq!(settings.today.start_time, parse_time("09:00"));
+        let past: Vec<_> = settings
+            .past_tasks
+            .iter()
+            .map(|day| (day.date, day.status))
+            .collect();
+        assert_eq!(
+            past,
+            vec![
+                (first, DayStatus::Tracked),
+                (first + Duration::days(1), DayStatus::DayOff),
+                (first + Duration::days(2), DayStatus::DayOff),
+            ]
+        );
+
+        // Running again on the same day changes nothing.
+        settings.roll_over(now);
+        assert_eq!(settings.past_tasks.len(), 3);
+    }
+
+    #[test]
+    fn split_session_never_creates_a_future_day() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let tomorrow = today.succ_opt().unwrap();
+        for rule in [
+            MidnightRule::Split,
+            MidnightRule::StartDay,
+            MidnightRule::EndDay,
+        ] {
+            let mut settings = UserSettings::new(today);
+            settings.midnight_rule = rule;
+            let result =
+                settings.record_session(session(at(today, "23:00"), at(tomorrow, "01:00")));
+            // Only the start-day rule keeps the whole session on today.
+            assert_eq!(result.is_ok(), rule == MidnightRule::StartDay);
+            assert!(settings.days().all(|day| day.date <= today));
+            assert!(settings.past_tasks.is_empty());
+        }
+    }
+
+    #[test]
+    fn overlap_is_found_across_the_filing_day() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let yesterday = today.pred_opt().unwrap();
+        let mut settings = UserSettings::new(today);
+        settings.midnight_rule = MidnightRule::EndDay;
+        settings
+            .record_session(session(at(yesterday, "22:00"), at(today, "00:30")))
+            .unwrap();
+        // Filed under today, but it overlaps work started yesterday evening.
+        let evening = session(at(yesterday, "21:00"), at(yesterday, "22:30"));
+        assert!(settings.overlapping_session(&evening).is_some());
+        let earlier = session(at(yesterday, "20:00"), at(yesterday, "21:00"));
+        assert!(settings.overlapping_session(&earlier).is_none());
+    }
+
+    #[test]
+    fn undoing_a_stop_restarts_the_timer() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let mut settings = UserSettings::new(today);
+        settings.active_session = Some(ActiveSession {
+            category: "Coding".to_string(),
+            start: at(today, "09:00"),
+            note: None,
+        });
+        settings.stop_timer(at(today, "10:00"), None).unwrap();
+        assert!(settings.active_session.is_none());
+        assert_eq!(settings.today.sessions.len(), 1);
+
+        settings.undo().unwrap();
+        assert!(settings.today.sessions.is_empty());
+        let restarted = settings.active_session.as_ref().unwrap();
+        assert_eq!(restarted.start, at(today, "09:00"));
+    }
+
+    #[test]
+    fn refused_session_leaves_nothing_behind() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let mut settings = UserSettings::new(today);
+        let tomorrow = today.succ_opt().unwrap();
+        assert!(settings
+            .record_session(session(at(today, "23:00"), at(tomorrow, "01:00")))
+            .is_err());
+        assert!(settings.today.sessions.is_empty());
+        assert_eq!(settings.next_session_id, 1);
+        assert!(settings.journal.is_empty());
+        assert!(settings.mark_day(tomorrow, DayStatus::Holiday).is_err());
+    }
+
+    #[test]
+    fn edit_across_midnight_is_split() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let yesterday = today.pred_opt().unwrap();
+        let mut settings = UserSettings::new(today);
+        settings.past_tasks.push(TodaysTasks::new(yesterday, None));
+        settings
+            .record_session(session(at(yesterday, "22:00"), at(yesterday, "23:30")))
+            .unwrap();
+
+        let edited = settings
+            .edit_session(1, None, |session, _| {
+                session.end = at(today, "01:30");
+                Ok(())
+            })
+            .unwrap();
+        let stored: Vec<_> = edited
+            .iter()
+            .map(|part| (part.id, settings.filing_date(part)))
+            .collect();
+        assert_eq!(stored, vec![(1, yesterday), (2, today)]);
+        assert_eq!(settings.day(yesterday).unwrap().minutes_for("Coding"), 120);
+        assert_eq!(settings.today.minutes_for("Coding"), 90);
+
+        settings.undo().unwrap();
+        assert!(settings.today.sessions.is_empty());
+        assert_eq!(settings.day(yesterday).unwrap().minutes_for("Coding"), 90);
+    }
+}
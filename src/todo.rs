@@ -0,0 +1,98 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TodoStatus {
+    Open,
+    InProgress,
+    Done,
+    Cancelled,
+}
+
+impl TodoStatus {
+    pub fn is_open(self) -> bool {
+        matches!(self, TodoStatus::Open | TodoStatus::InProgress)
+    }
+}
+
+impl fmt::Display for TodoStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TodoStatus::Open => "open",
+            TodoStatus::InProgress => "in progress",
+            TodoStatus::Done => "done",
+            TodoStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TodoItem {
+    pub id: u32,
+    pub title: String,
+    pub status: TodoStatus,
+    pub created: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<NaiveDateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct TodoList(Vec<TodoItem>);
+
+impl TodoList {
+    pub fn items(&self) -> &[TodoItem] {
+        &self.0
+    }
+
+    pub fn open(&self) -> impl Iterator<Item = &TodoItem> {
+        self.0.iter().filter(|item| item.status.is_open())
+    }
+
+    pub fn add(&mut self, title: String, category: Option<String>, now: NaiveDateTime) -> u32 {
+        let id = self.0.iter().map(|item| item.id).max().unwrap_or(0) + 1;
+        self.0.push(TodoItem {
+            id,
+            title,
+            status: TodoStatus::Open,
+            created: now,
+            completed: None,
+            category,
+        });
+        id
+    }
+
+    /// Moves an item to a new status, stamping or clearing its completion time.
+    pub fn set_status(
+        &mut self,
+        id: u32,
+        status: TodoStatus,
+        now: NaiveDateTime,
+    ) -> Result<&TodoItem, String> {
+        let item = self
+            .0
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("No to-do item with id {}.", id))?;
+        if item.status == status {
+            return Err(format!("#{} is already {}.", id, status));
+        }
+        item.status = status;
+        item.completed = if status.is_open() { None } else { Some(now) };
+        Ok(item)
+    }
+
+    /// Keeps items filed under a renamed category's new name.
+    pub fn rename_category(&mut self, old_name: &str, new_name: &str) {
+        for item in &mut self.0 {
+            if item.category.as_deref() == Some(old_name) {
+                item.category = Some(new_name.to_string());
+            }
+        }
+    }
+}
@@ -1,65 +1,110 @@
-use crate::serialization::save_user_settings; // Import save_user_settings function from serialization module
-use crate::task::{Task, UserSettings};
+use crate::clock::parse_time_input;
+use crate::schedule::ScheduledDay;
+use crate::serialization::{latest_backup, restore_backup, update_user_settings, StorageError};
+use crate::stats::{day_breakdown, display_current_goals, SortOrder};
+use crate::task::{Session, TodaysTasks, UserSettings};
+use crate::todo::{TodoItem, TodoStatus};
 use chrono::Datelike;
-use chrono::{Duration, NaiveTime, Utc};
+use chrono::{NaiveDateTime, NaiveTime};
 use colored::*;
-use std::io::{self};
+use std::io::{self, IsTerminal};
 
-pub fn get_time_from_user(prompt: &str) -> NaiveTime {
+pub fn get_time_from_user(prompt: &str, user_settings: &UserSettings) -> NaiveTime {
     loop {
         println!("{}", prompt);
         let mut time_input = String::new();
         io::stdin()
             .read_line(&mut time_input)
             .expect("Failed to read line");
-        let time_input = time_input.trim();
-        if time_input.is_empty() || time_input.eq_ignore_ascii_case("now") {
-            return (Utc::now() + Duration::hours(2)).time();
-        } else if time_input.len() == 4 && time_input.chars().all(char::is_numeric) {
-            let formatted_time = format!("{}:{}", &time_input[0..2], &time_input[2..4]);
-            match NaiveTime::parse_from_str(&formatted_time, "%H:%M") {
-                Ok(time) => return time,
-                Err(_) => println!("Invalid time format. Please try again."),
-            }
-        } else {
-            match NaiveTime::parse_from_str(time_input, "%H:%M") {
-                Ok(time) => return time,
-                Err(_) => println!("Invalid time format. Please try again."),
-            }
+        match parse_time_input(&time_input, user_settings.now().time()) {
+            Some(time) => return time,
+            None => println!("Invalid time format. Please try again."),
         }
     }
 }
 
+/// Points out, without refusing it, a session that overlaps one already logged.
+pub fn warn_if_overlapping(user_settings: &UserSettings, session: &Session) {
+    if let Some(existing) = user_settings.overlapping_session(session) {
+        println!(
+            "{}",
+            format!(
+                "Note: this overlaps {} from {} to {}.",
+                existing.category,
+                existing.start.format("%H:%M"),
+                existing.end.format("%H:%M")
+            )
+            .yellow()
+        );
+    }
+}
+
+/// Prints today's progress. The start and end of the day are only shown when
+/// known, so this never has to prompt.
 pub fn display_summary(
-    user_settings: &mut UserSettings,
-    current_time: &chrono::DateTime<Utc>,
-    end_of_day: chrono::DateTime<Utc>,
+    user_settings: &UserSettings,
+    current_time: &NaiveDateTime,
+    start_time: Option<NaiveTime>,
+    end_of_day: Option<NaiveDateTime>,
 ) {
-    let start_time = user_settings.get_start_time();
-    println!(
-        "Welcome to a new day. You started today at {}. Current time is {}",
-        start_time.format("%H:%M:%S"),
-        current_time.format("%H:%M:%S")
-    );
+    match start_time {
+        Some(start_time) => println!(
+            "Welcome to a new day. You started today at {}. Current time is {}",
+            start_time.format("%H:%M:%S"),
+            current_time.format("%H:%M:%S")
+        ),
+        None => println!("Current time is {}", current_time.format("%H:%M:%S")),
+    }
 
-    let current_weekday = current_time.date_naive().weekday();
+    // After switching to a timezone that is behind, it can still be the day
+    // before `today`, which then has its own record.
+    let date = user_settings.current_date();
+    let earlier_day;
+    let day = match user_settings.day(date) {
+        Some(day) => day,
+        None => {
+            earlier_day = TodaysTasks::new(date, None);
+            &earlier_day
+        }
+    };
     println!(
         "Schedule for {}, {}",
-        current_time.format("%d/%m/%Y"),
-        current_weekday
+        date.format("%d/%m/%Y"),
+        date.weekday()
     );
+    match user_settings.scheduled_day(date) {
+        Some(ScheduledDay::Off) => println!("Today is not a scheduled work day."),
+        Some(hours) => println!("Working hours: {}", hours),
+        None => {}
+    }
 
-    let remaining_hours = end_of_day - *current_time;
-    let hours_left = remaining_hours.num_hours();
-    let minutes_left = remaining_hours.num_minutes() % 60;
+    if let Some(active) = &user_settings.active_session {
+        let elapsed = (*current_time - active.start).num_minutes().max(0);
+        println!(
+            "{}",
+            format!(
+                "Timer running: {} since {} ({} hours and {} minutes)",
+                active.category,
+                active.start.format("%H:%M"),
+                elapsed / 60,
+                elapsed % 60
+            )
+            .cyan()
+        );
+    }
 
-    println!(
-        "You have {} hours and {} minutes left.",
-        hours_left, minutes_left
-    );
+    if let Some(end_of_day) = end_of_day {
+        let remaining_hours = end_of_day - *current_time;
+        let hours_left = remaining_hours.num_hours();
+        let minutes_left = remaining_hours.num_minutes() % 60;
+
+        println!(
+            "You have {} hours and {} minutes left.",
+            hours_left, minutes_left
+        );
+    }
 
-    let total_productivity_minutes: u64 = user_settings
-        .today
+    let total_productivity_minutes: u64 = day
         .todays_tasks
         .values()
         .map(|task| task.minutes_spent)
@@ -77,7 +122,21 @@ pub fn display_summary(
         .green()
     );
 
-    if hours_productive >= 8 {
+    if let Some(scheduled) = user_settings
+        .scheduled_minutes(day)
+        .filter(|scheduled| *scheduled > 0)
+    {
+        println!(
+            "That is {}% of the {} hours and {} minutes scheduled for today.",
+            total_productivity_minutes * 100 / scheduled,
+            scheduled / 60,
+            scheduled % 60
+        );
+    }
+
+    if !user_settings.goals.is_empty() {
+        display_current_goals(user_settings);
+    } else if hours_productive >= 8 {
         println!("You had a good day")
     } else if hours_productive >= 4 {
         println!("You had a good day. It could get better.")
@@ -85,72 +144,76 @@ pub fn display_summary(
         println!("Keep Going")
     }
 
-    for (task, task_data) in &user_settings.today.todays_tasks {
-        let task_hours = task_data.minutes_spent / 60;
-        let task_minutes = task_data.minutes_spent % 60;
-        println!(
-            "{}: {} hours and {} minutes",
-            task, task_hours, task_minutes
-        );
-    }
+    display_day_breakdown(user_settings, day);
 
-    for (chore, chore_data) in &user_settings.today.todays_chores {
-        let chore_hours = chore_data.minutes_spent / 60;
-        let chore_minutes = chore_data.minutes_spent % 60;
-        println!(
-            "{}: {} hours and {} minutes",
-            chore, chore_hours, chore_minutes
-        );
+    let open_items: Vec<_> = user_settings.todos.open().collect();
+    if !open_items.is_empty() {
+        println!("\n{}", "Open to-do items".bold());
+        for item in open_items {
+            display_todo(user_settings, item);
+        }
     }
+}
+
+pub fn display_todo(user_settings: &UserSettings, item: &TodoItem) {
+    let category = item
+        .category
+        .as_ref()
+        .map(|name| format!(" [{}]", user_settings.categories.paint(name)))
+        .unwrap_or_default();
+    let status = match item.status {
+        TodoStatus::Open => item.status.to_string().normal(),
+        TodoStatus::InProgress => item.status.to_string().yellow(),
+        TodoStatus::Done => item.status.to_string().green(),
+        TodoStatus::Cancelled => item.status.to_string().dimmed(),
+    };
+    println!("#{:<3} {:<12} {}{}", item.id, status, item.title, category);
+}
 
-    for (entertainment, entertainment_data) in &user_settings.today.todays_entertainment {
-        let entertainment_hours = entertainment_data.minutes_spent / 60;
-        let entertainment_minutes = entertainment_data.minutes_spent % 60;
+fn display_day_breakdown(user_settings: &UserSettings, day: &TodaysTasks) {
+    for (task, minutes) in day_breakdown(day, SortOrder::default()) {
         println!(
             "{}: {} hours and {} minutes",
-            entertainment, entertainment_hours, entertainment_minutes
+            user_settings.categories.paint(&task),
+            minutes / 60,
+            minutes % 60
         );
     }
 }
 
-pub fn prompt_task(user_settings: &mut UserSettings) -> bool {
-    let mut total_productivity_minutes: u64 = user_settings
-        .today
-        .todays_tasks
-        .values()
-        .map(|task| task.minutes_spent)
-        .sum();
-
+/// Asks for one task and records it. Returns `false` once the user exits.
+pub fn prompt_task(user_settings: &mut UserSettings) -> Result<bool, StorageError> {
     loop {
-        println!("\nEnter task code (C for Coding, R for Reading, A for Action, W for Writing, L for Learning, Ch for Chores, E for Entertainment, or X to exit):");
+        println!(
+            "\nEnter task code ({}, or X to exit):",
+            user_settings.categories.prompt_list()
+        );
         let mut task_code = String::new();
-        io::stdin()
+        let bytes_read = io::stdin()
             .read_line(&mut task_code)
             .expect("Failed to read line");
-        let task_code = task_code.trim().to_lowercase();
+        let task_code = task_code.trim();
 
-        if task_code == "x" {
-            break;
+        // End of input counts as exiting, so piped input cannot loop forever.
+        if bytes_read == 0 || task_code.eq_ignore_ascii_case("x") {
+            return Ok(false);
         }
 
-        let task_name = match task_code.as_str() {
-            "c" => "Coding",
-            "r" => "Reading",
-            "a" => "Action",
-            "w" => "Writing",
-            "l" => "Learning",
-            "ch" => "Chores",
-            "e" => "Entertainment",
-            _ => {
+        let task_name = match user_settings.categories.by_code(task_code) {
+            Some(category) => category.name.clone(),
+            None => {
                 println!("Invalid task code. Please try again.");
                 continue;
             }
         };
 
-        let start_time_prompt = get_time_from_user(&format!(
-            "Enter start time for {} (HH:MM) or 'now' for the current time:",
-            task_name
-        ));
+        let start_time_prompt = get_time_from_user(
+            &format!(
+                "Enter start time for {} (HH:MM) or 'now' for the current time:",
+                task_name
+            ),
+            user_settings,
+        );
 
         let end_time_prompt = format!(
             "Started at {}. Enter end time for {} (HH:MM) or 'now' for the current time (type 'cancel' to cancel this task):",
@@ -170,116 +233,108 @@ pub fn prompt_task(user_settings: &mut UserSettings) -> bool {
 
             if end_time_input == "cancel" {
                 println!("Task cancelled. Returning to task code prompt.");
-                break;
+                return Ok(true);
             }
 
-            let end_time = if end_time_input.is_empty() || end_time_input == "now" {
-                (Utc::now() + Duration::hours(2)).time()
-            } else if end_time_input.len() == 4 && end_time_input.chars().all(char::is_numeric) {
-                let formatted_time = format!("{}:{}", &end_time_input[0..2], &end_time_input[2..4]);
-                match NaiveTime::parse_from_str(&formatted_time, "%H:%M") {
-                    Ok(time) => time,
-                    Err(_) => {
-                        println!("Invalid time format. Please try again.");
-                        continue;
-                    }
-                }
-            } else {
-                match NaiveTime::parse_from_str(&end_time_input, "%H:%M") {
-                    Ok(time) => time,
-                    Err(_) => {
-                        println!("Invalid time format. Please try again.");
-                        continue;
-                    }
-                }
+            let Some(end_time) = parse_time_input(&end_time_input, user_settings.now().time())
+            else {
+                println!("Invalid time format. Please try again.");
+                continue;
             };
 
-            if end_time < start_time_prompt {
-                println!("End time cannot be earlier than start time. Please try again.");
-            } else {
-                let duration = end_time.signed_duration_since(start_time_prompt);
-
-                println!(
-                    "\nYou Spent {} Minutes {}",
-                    duration.num_minutes(),
-                    task_name
-                );
-
-                let duration_minutes = duration.num_minutes() as u64;
-
-                if task_name == "Chores" {
-                    let task_entry = user_settings
-                        .today
-                        .todays_chores
-                        .entry(task_name.to_string())
-                        .or_insert(Task { minutes_spent: 0 });
-                    task_entry.minutes_spent += duration_minutes;
-                } else if task_name == "Entertainment" {
-                    let task_entry = user_settings
-                        .today
-                        .todays_entertainment
-                        .entry(task_name.to_string())
-                        .or_insert(Task { minutes_spent: 0 });
-                    task_entry.minutes_spent += duration_minutes;
+            let now = user_settings.now();
+            let (start, end) = user_settings.recent_span(start_time_prompt, end_time);
+            let duration = end - start;
+
+            println!(
+                "\nYou Spent {} Minutes {}",
+                duration.num_minutes(),
+                task_name
+            );
+
+            println!("Add a note for this session (optional, press Enter to skip):");
+            let mut note = String::new();
+            io::stdin()
+                .read_line(&mut note)
+                .expect("Failed to read line");
+            let note = note.trim();
+
+            let session = Session {
+                id: 0,
+                category: task_name.clone(),
+                start,
+                end,
+                note: if note.is_empty() {
+                    None
                 } else {
-                    let task_entry = user_settings
-                        .today
-                        .todays_tasks
-                        .entry(task_name.to_string())
-                        .or_insert(Task { minutes_spent: 0 });
-                    task_entry.minutes_spent += duration_minutes;
-
-                    total_productivity_minutes += duration_minutes;
-                }
-
-                save_user_settings(user_settings);
-
-                let hours_productive = total_productivity_minutes / 60;
-                let minutes_productive = total_productivity_minutes % 60;
-
-                println!(
-                    "{}",
-                    format!(
-                        "You have been productive for {} hours and {} minutes",
-                        hours_productive, minutes_productive
-                    )
-                    .green()
-                );
-
-                for (task, task_data) in &user_settings.today.todays_tasks {
-                    let task_hours = task_data.minutes_spent / 60;
-                    let task_minutes = task_data.minutes_spent % 60;
-                    println!(
-                        "{}: {} hours and {} minutes",
-                        task, task_hours, task_minutes
-                    );
-                }
-
-                // Print chores separately
-                for (chore, chore_data) in &user_settings.today.todays_chores {
-                    let chore_hours = chore_data.minutes_spent / 60;
-                    let chore_minutes = chore_data.minutes_spent % 60;
-                    println!(
-                        "{}: {} hours and {} minutes",
-                        chore, chore_hours, chore_minutes
-                    );
-                }
-
-                // Print entertainment separately
-                for (entertainment, entertainment_data) in &user_settings.today.todays_entertainment
-                {
-                    let entertainment_hours = entertainment_data.minutes_spent / 60;
-                    let entertainment_minutes = entertainment_data.minutes_spent % 60;
-                    println!(
-                        "{}: {} hours and {} minutes",
-                        entertainment, entertainment_hours, entertainment_minutes
-                    );
-                }
-
-                break;
+                    Some(note.to_string())
+                },
+            };
+
+            warn_if_overlapping(user_settings, &session);
+
+            // The prompt may have been open since before midnight.
+            let recorded = update_user_settings(user_settings, |settings| {
+                settings.roll_over(now);
+                settings.record_session(session)
+            })?;
+            if let Err(error) = recorded {
+                println!("{}", error.red());
+                continue;
             }
+            let total_productivity_minutes: u64 = user_settings
+                .today
+                .todays_tasks
+                .values()
+                .map(|task| task.minutes_spent)
+                .sum();
+
+            let hours_productive = total_productivity_minutes / 60;
+            let minutes_productive = total_productivity_minutes % 60;
+
+            println!(
+                "{}",
+                format!(
+                    "You have been productive for {} hours and {} minutes",
+                    hours_productive, minutes_productive
+                )
+                .green()
+            );
+
+            display_day_breakdown(user_settings, &user_settings.today);
+
+            return Ok(true);
         }
     }
+}
 
-    true
+/// Asks whether to replace an unreadable settings file with the newest backup.
+/// Returns whether a backup was restored.
+pub fn offer_backup_restore() -> Result<bool, StorageError> {
+    let Some(backup_path) = latest_backup()? else {
+        println!("No backup is available to restore from.");
+        return Ok(false);
+    };
+    if !io::stdin().is_terminal() {
+        println!(
+            "The latest backup is {}. Run `crawl-todo restore` to choose a backup to restore.",
+            backup_path.display()
+        );
+        return Ok(false);
+    }
+
+    println!(
+        "Restore from the latest backup, {}? The current file will be kept as a backup. [y/N]",
+        backup_path.display()
+    );
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .expect("Failed to read line");
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        return Ok(false);
+    }
+    restore_backup(&backup_path)?;
+    println!("{}", "Restored the settings file from backup.".green());
+    Ok(true)
 }